@@ -0,0 +1,95 @@
+//! Cancelling the translation in flight, from Esc, the Stop button or the
+//! hotkey pressed again.
+
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static WAKE: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Marks a translation as running until dropped.
+pub struct Running(());
+
+impl Running {
+    pub fn start() -> Self {
+        CANCELLED.store(false, Ordering::SeqCst);
+        RUNNING.store(true, Ordering::SeqCst);
+        Running(())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// For work that blocks the thread (local inference) and so has to poll.
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Cancel the running translation. Returns false if nothing was running.
+pub fn cancel() -> bool {
+    if !is_running() {
+        return false;
+    }
+    CANCELLED.store(true, Ordering::SeqCst);
+    WAKE.notify_waiters();
+    true
+}
+
+/// Drive `fut` until it completes or the translation is cancelled, in which
+/// case `fut` is dropped (closing its HTTP connection) and `None` returned.
+pub async fn run<T>(fut: impl Future<Output = T>) -> Option<T> {
+    tokio::select! {
+        // A result that is ready only because of the cancel is not one
+        biased;
+        _ = cancelled() => None,
+        res = fut => Some(res),
+    }
+}
+
+/// Error for blocking work that noticed [`is_cancelled`] and stopped early.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Translation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+async fn cancelled() {
+    loop {
+        // Register before checking the flag so a cancel in between is not missed
+        let woken = WAKE.notified();
+        if is_cancelled() {
+            return;
+        }
+        woken.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_wins_over_a_ready_result() {
+        let _running = Running::start();
+        assert_eq!(run(async { 1 }).await, Some(1));
+        assert!(cancel());
+        // Blocking work finishes in the same poll the cancel is seen
+        assert_eq!(run(async { 2 }).await, None);
+    }
+}
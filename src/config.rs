@@ -1,5 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -11,9 +12,129 @@ pub struct Config {
     #[serde(default = "default_hotkey")]
     pub hotkey: String,
     #[serde(default = "default_api_type")]
-    pub api_type: String,  // "openai", "ollama", "openai-compatible"
+    pub api_type: String,  // "openai", "ollama", "google_free", ... (see providers::PROVIDERS)
     #[serde(default = "default_api_base")]
     pub api_base: String,  // Custom API endpoint
+    #[serde(default)]
+    pub deepl_formality: String,  // "", "more", "less", "prefer_more", "prefer_less"
+    #[serde(default)]
+    pub deepl_glossary_id: String,
+    #[serde(default)]
+    pub deepl_source_lang: String,  // Empty = auto-detect; required with a glossary
+    #[serde(default)]
+    pub azure_deployment: String,
+    #[serde(default = "default_azure_api_version")]
+    pub azure_api_version: String,
+    #[serde(default)]
+    pub ollama_num_ctx: Option<u32>,
+    #[serde(default)]
+    pub ollama_temperature: Option<f32>,
+    #[serde(default)]
+    pub ollama_keep_alive: Option<String>,  // e.g. "5m", "1h", or seconds ("-1" = keep loaded)
+    #[serde(default)]
+    pub fallback_providers: Vec<EndpointConfig>,  // Tried in order when the primary backend fails
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,  // Retries on 429/502/503/504 and connection resets, per backend
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,  // Longest answer requested from the backend
+    #[serde(default = "default_segment_tokens")]
+    pub segment_tokens: u32,  // Longer texts are translated in segments of about this many tokens
+    #[serde(default = "default_segment_concurrency")]
+    pub segment_concurrency: u32,  // Segments translated at the same time; 1 = one after another
+    #[serde(default)]
+    pub queue_jobs: bool,  // A hotkey press on new clipboard content waits for the running translation instead of cancelling it
+    #[serde(default)]
+    pub compare_mode: bool,  // Translate with the primary and every compare_providers entry side by side
+    #[serde(default)]
+    pub compare_providers: Vec<EndpointConfig>,
+    #[serde(default)]
+    pub proxy_url: String,  // http://, https://, socks5:// or socks5h://; empty uses HTTP_PROXY/HTTPS_PROXY/ALL_PROXY
+    #[serde(default)]
+    pub proxy_username: String,
+    #[serde(default)]
+    pub proxy_password: String,
+    #[serde(default = "default_no_proxy")]
+    pub no_proxy: String,  // Comma-separated hosts, domains and CIDRs reached directly
+    #[serde(default)]
+    pub timeouts: BTreeMap<String, TimeoutConfig>,  // Per api_type, e.g. {"ollama": {"first_byte_secs": 600}}
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,  // Sent with every request to the primary backend, e.g. {"X-Team": "docs"}
+    #[serde(default)]
+    pub extra_query: BTreeMap<String, String>,  // Added to every request URL of the primary backend
+    #[serde(default)]
+    pub stream_usage: Option<bool>,  // Ask an OpenAI-style stream for token counts; None = only api.openai.com and Azure 2024-09-01+
+    #[serde(default)]
+    pub rate_limits: BTreeMap<String, RateLimitConfig>,  // Per api_type, e.g. {"openai": {"requests_per_minute": 60}}
+    #[serde(default)]
+    pub ca_certs: Vec<String>,  // PEM files with extra root certificates, e.g. a company CA
+    #[serde(default)]
+    pub client_cert: String,  // PEM client certificate for mutual TLS
+    #[serde(default)]
+    pub client_key: String,  // PEM private key, if not in client_cert
+    #[serde(default)]
+    pub allow_invalid_certs: bool,  // Skip TLS certificate checks for the primary backend. Insecure
+    #[serde(default)]
+    pub prices: BTreeMap<String, ModelPrice>,  // Per model, to show what translations cost
+    #[serde(default)]
+    pub stardict_dirs: Vec<String>,  // Folders with StarDict dictionaries, searched for words and short phrases
+    #[serde(default)]
+    pub local_model_path: String,  // GGUF file for the "local" backend (built with the local-llm feature)
+    #[serde(default)]
+    pub local_tokenizer_path: String,  // Empty = tokenizer.json next to the model
+    #[serde(default)]
+    pub local_threads: Option<usize>,  // CPU threads for local inference; None = all cores
+}
+
+/// An extra backend for `fallback_providers` or `compare_providers`. Empty fields use the primary settings when it
+/// is the same `api_type`, and that backend's defaults otherwise.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointConfig {
+    pub api_type: String,
+    #[serde(default)]
+    pub api_base: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default)]
+    pub allow_invalid_certs: bool,  // Skip TLS certificate checks for this backend only. Insecure
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub extra_query: BTreeMap<String, String>,
+    #[serde(default)]
+    pub stream_usage: Option<bool>,
+}
+
+/// Timeout overrides for one backend, in seconds; unset fields keep the defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeoutConfig {
+    #[serde(default)]
+    pub connect_secs: Option<u64>,
+    #[serde(default)]
+    pub first_byte_secs: Option<u64>,
+    #[serde(default)]
+    pub idle_secs: Option<u64>,
+}
+
+/// Client-side limits for one backend; unset fields do not limit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+    #[serde(default)]
+    pub max_concurrent: Option<u32>,
+}
+
+/// What a model costs, in dollars per million tokens.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ModelPrice {
+    #[serde(default)]
+    pub input: f64,
+    #[serde(default)]
+    pub output: f64,
 }
 
 fn default_api_type() -> String {
@@ -24,6 +145,31 @@ fn default_api_base() -> String {
     "https://api.openai.com/v1".to_string()
 }
 
+fn default_azure_api_version() -> String {
+    "2024-06-01".to_string()
+}
+
+fn default_no_proxy() -> String {
+    "localhost,127.0.0.1,::1".to_string()
+}
+
+fn default_max_tokens() -> u32 {
+    1024
+}
+
+fn default_segment_tokens() -> u32 {
+    // Leaves room for translations that come out longer than the source
+    600
+}
+
+fn default_segment_concurrency() -> u32 {
+    1
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
 fn default_hotkey() -> String {
     "Alt+F3".to_string()
 }
@@ -37,6 +183,40 @@ impl Default for Config {
             hotkey: default_hotkey(),
             api_type: default_api_type(),
             api_base: default_api_base(),
+            deepl_formality: String::new(),
+            deepl_glossary_id: String::new(),
+            deepl_source_lang: String::new(),
+            azure_deployment: String::new(),
+            azure_api_version: default_azure_api_version(),
+            ollama_num_ctx: None,
+            ollama_temperature: None,
+            ollama_keep_alive: None,
+            fallback_providers: Vec::new(),
+            max_retries: default_max_retries(),
+            max_tokens: default_max_tokens(),
+            segment_tokens: default_segment_tokens(),
+            segment_concurrency: default_segment_concurrency(),
+            queue_jobs: false,
+            compare_mode: false,
+            compare_providers: Vec::new(),
+            proxy_url: String::new(),
+            proxy_username: String::new(),
+            proxy_password: String::new(),
+            no_proxy: default_no_proxy(),
+            timeouts: BTreeMap::new(),
+            extra_headers: BTreeMap::new(),
+            extra_query: BTreeMap::new(),
+            stream_usage: None,
+            rate_limits: BTreeMap::new(),
+            ca_certs: Vec::new(),
+            client_cert: String::new(),
+            client_key: String::new(),
+            allow_invalid_certs: false,
+            prices: BTreeMap::new(),
+            stardict_dirs: Vec::new(),
+            local_model_path: String::new(),
+            local_tokenizer_path: String::new(),
+            local_threads: None,
         }
     }
 }
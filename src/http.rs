@@ -0,0 +1,261 @@
+//! HTTP clients for the backends, built from the network settings, and the
+//! per-backend timeouts applied while sending and reading.
+
+use crate::config::Config;
+use crate::logger;
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Everything a client is built from. Clients are cached per distinct value,
+/// so asking for one on every request keeps pooled connections.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClientOptions {
+    proxy_url: String,
+    proxy_username: String,
+    proxy_password: String,
+    no_proxy: String,
+    ca_certs: Vec<String>,
+    client_cert: String,
+    client_key: String,
+    allow_invalid_certs: bool,
+    timeouts: Timeouts,
+    extra_headers: BTreeMap<String, String>,
+    extra_query: BTreeMap<String, String>,
+}
+
+impl ClientOptions {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            proxy_url: cfg.proxy_url.trim().to_string(),
+            proxy_username: cfg.proxy_username.clone(),
+            proxy_password: cfg.proxy_password.clone(),
+            no_proxy: cfg.no_proxy.clone(),
+            ca_certs: cfg.ca_certs.iter().map(|path| path.trim().to_string()).filter(|path| !path.is_empty()).collect(),
+            client_cert: cfg.client_cert.trim().to_string(),
+            client_key: cfg.client_key.trim().to_string(),
+            allow_invalid_certs: cfg.allow_invalid_certs,
+            timeouts: Timeouts::from_config(cfg),
+            extra_headers: cfg.extra_headers.clone(),
+            extra_query: cfg.extra_query.clone(),
+        }
+    }
+
+    /// Add the endpoint's extra headers and query parameters to `request`.
+    /// The headers replace any of the same name set before.
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> anyhow::Result<reqwest::RequestBuilder> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.extra_headers {
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid header name in extra_headers: {:?}", name))?;
+            let value = HeaderValue::from_str(value.trim())
+                .map_err(|_| anyhow::anyhow!("Invalid value for header {} in extra_headers", name))?;
+            headers.insert(name, value);
+        }
+        let mut request = request.headers(headers);
+        if !self.extra_query.is_empty() {
+            request = request.query(&self.extra_query);
+        }
+        Ok(request)
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+}
+
+/// How long to wait at each stage of a request. There is no limit on the
+/// whole request, so long translations can stream for as long as they need.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Timeouts {
+    /// Establishing the connection, including TLS
+    pub connect: Duration,
+    /// From sending the request until the response headers arrive
+    pub first_byte: Duration,
+    /// Between two chunks of the response body
+    pub idle: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self { connect: Duration::from_secs(10), first_byte: Duration::from_secs(60), idle: Duration::from_secs(30) }
+    }
+}
+
+impl Timeouts {
+    /// Defaults for the backend selected by `cfg.api_type`, overridden by its
+    /// entry in `cfg.timeouts`.
+    fn from_config(cfg: &Config) -> Self {
+        let id = crate::providers::info(&cfg.api_type).id;
+        let mut timeouts = match id {
+            // Self-hosted models may have to be loaded from disk before the first token
+            "ollama" | "libretranslate" => Self { first_byte: Duration::from_secs(300), ..Self::default() },
+            _ => Self::default(),
+        };
+        if let Some(custom) = cfg.timeouts.get(id) {
+            let secs = |value: Option<u64>, default: Duration| value.filter(|&s| s > 0).map_or(default, Duration::from_secs);
+            timeouts.connect = secs(custom.connect_secs, timeouts.connect);
+            timeouts.first_byte = secs(custom.first_byte_secs, timeouts.first_byte);
+            timeouts.idle = secs(custom.idle_secs, timeouts.idle);
+        }
+        timeouts
+    }
+}
+
+/// A request that ran into one of its [`Timeouts`].
+#[derive(Debug)]
+pub enum Timeout {
+    Connect(Duration),
+    FirstByte(Duration),
+    Idle(Duration),
+}
+
+impl std::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect(d) => write!(f, "Could not connect within {}s (connect timeout)", d.as_secs()),
+            Self::FirstByte(d) => write!(
+                f,
+                "No response within {}s (first-byte timeout). A model that is still loading may need a longer first_byte_secs",
+                d.as_secs()
+            ),
+            Self::Idle(d) => write!(f, "The response stalled for {}s (idle timeout)", d.as_secs()),
+        }
+    }
+}
+
+impl std::error::Error for Timeout {}
+
+static CLIENTS: Lazy<Mutex<HashMap<ClientOptions, reqwest::Client>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The shared client for `options`.
+pub fn client(options: &ClientOptions) -> anyhow::Result<reqwest::Client> {
+    let mut clients = CLIENTS.lock().unwrap();
+    if let Some(client) = clients.get(options) {
+        return Ok(client.clone());
+    }
+    let client = builder(options)?.connect_timeout(options.timeouts.connect).build()?;
+    if options.allow_invalid_certs {
+        logger::log("WARNING: TLS certificate verification is DISABLED for an endpoint (allow_invalid_certs)");
+        crate::toast("Echo", "⚠ Certificate checks are OFF for an endpoint. Anyone on the network can read and alter its traffic.");
+    }
+    clients.insert(options.clone(), client.clone());
+    Ok(client)
+}
+
+/// A client builder with the proxy and certificates applied. Without a proxy
+/// URL, reqwest reads HTTP_PROXY, HTTPS_PROXY, ALL_PROXY and NO_PROXY from the
+/// environment.
+pub fn builder(options: &ClientOptions) -> anyhow::Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder();
+    if !options.proxy_url.is_empty() {
+        // The URL may carry credentials, so it is left out of the error
+        let mut proxy = reqwest::Proxy::all(&options.proxy_url)
+            .map_err(|e| anyhow::anyhow!("Invalid proxy URL (expected http://, https://, socks5:// or socks5h://): {}", e))?;
+        if !options.proxy_username.is_empty() {
+            proxy = proxy.basic_auth(&options.proxy_username, &options.proxy_password);
+        }
+        builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_string(&options.no_proxy)));
+    }
+
+    // Trusted in addition to the built-in roots
+    for path in &options.ca_certs {
+        let pem = read_pem(path, "CA certificate")?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| anyhow::anyhow!("Invalid CA certificate {}: {}", path, e))?;
+        if certs.is_empty() {
+            anyhow::bail!("No certificates found in {}", path);
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if !options.client_cert.is_empty() {
+        // rustls wants the certificate chain and the key in one PEM buffer
+        let mut pem = read_pem(&options.client_cert, "client certificate")?;
+        if !options.client_key.is_empty() {
+            pem.push(b'\n');
+            pem.extend(read_pem(&options.client_key, "client key")?);
+        }
+        let identity = reqwest::Identity::from_pem(&pem)
+            .map_err(|e| anyhow::anyhow!("Invalid client certificate or key {}: {}", options.client_cert, e))?;
+        builder = builder.identity(identity);
+    }
+
+    if options.allow_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// Idle timeout of the backend a response came from, for [`next_chunk`].
+#[derive(Clone, Copy)]
+struct IdleTimeout(Duration);
+
+/// Send `request`, giving up on the response headers after the first-byte
+/// timeout. The response remembers the idle timeout for reading its body.
+pub async fn send(request: reqwest::RequestBuilder, timeouts: Timeouts) -> anyhow::Result<reqwest::Response> {
+    let mut resp = match tokio::time::timeout(timeouts.first_byte, request.send()).await {
+        Err(_) => return Err(Timeout::FirstByte(timeouts.first_byte).into()),
+        Ok(Err(e)) if e.is_connect() && e.is_timeout() => return Err(Timeout::Connect(timeouts.connect).into()),
+        Ok(resp) => resp?,
+    };
+    resp.extensions_mut().insert(IdleTimeout(timeouts.idle));
+    Ok(resp)
+}
+
+/// The next chunk of the body, or `None` at the end. Fails with
+/// [`Timeout::Idle`] if the server goes quiet for too long.
+pub async fn next_chunk(resp: &mut reqwest::Response) -> anyhow::Result<Option<impl std::ops::Deref<Target = [u8]>>> {
+    let Some(&IdleTimeout(idle)) = resp.extensions().get::<IdleTimeout>() else {
+        return Ok(resp.chunk().await?);
+    };
+    match tokio::time::timeout(idle, resp.chunk()).await {
+        Err(_) => Err(Timeout::Idle(idle).into()),
+        Ok(chunk) => Ok(chunk?),
+    }
+}
+
+/// The whole body, read with [`next_chunk`].
+pub async fn bytes(mut resp: reqwest::Response) -> anyhow::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = next_chunk(&mut resp).await? {
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// The body parsed as JSON, read with [`next_chunk`].
+pub async fn json<T: serde::de::DeserializeOwned>(resp: reqwest::Response) -> anyhow::Result<T> {
+    let body = bytes(resp).await?;
+    serde_json::from_slice(&body).map_err(|e| anyhow::anyhow!("Failed to parse response ({}): {}", e, String::from_utf8_lossy(&body)))
+}
+
+fn read_pem(path: &str, what: &str) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {} {}: {}", what, path, e))
+}
+
+/// Messages of the TLS backends (rustls, OpenSSL, SChannel) for a server
+/// certificate that does not chain to a trusted root.
+const UNTRUSTED_CERT: &[&str] = &["invalid peer certificate", "certificate verify failed", "certificate chain"];
+
+/// Spell out certificate failures, which the TLS backend reports several
+/// layers down a generic "error sending request".
+pub fn explain_tls_error(err: anyhow::Error) -> anyhow::Error {
+    let Some(cause) = err
+        .chain()
+        .map(|cause| cause.to_string())
+        .filter(|cause| UNTRUSTED_CERT.iter().any(|needle| cause.contains(needle)))
+        .last()
+    else {
+        return err;
+    };
+    let hint = format!(
+        "The server's TLS certificate was rejected ({}). If it uses a private CA, add the CA's PEM file under CA Certificates in settings",
+        cause
+    );
+    err.context(hint)
+}
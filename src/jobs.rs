@@ -0,0 +1,195 @@
+//! Translation jobs between the hotkey and the worker. Repeated presses on
+//! the same clipboard content are coalesced instead of each costing another
+//! API call.
+
+use crate::{cancel, logger, ui, ImageData};
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Presses of the same content closer together than this are one press
+/// (key repeat, impatient mashing).
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Clipboard content captured when the hotkey was pressed.
+pub struct Job {
+    pub text: String,
+    pub image: Option<ImageData>,
+    hash: u64,
+}
+
+impl Job {
+    pub fn new(text: String, image: Option<ImageData>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        if let Some(image) = &image {
+            image.bytes.hash(&mut hasher);
+        }
+        Self { text, image, hash: hasher.finish() }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    pending: VecDeque<Job>,
+    /// Hash of the job the worker is on
+    running: Option<u64>,
+    last_press: Option<(u64, Instant)>,
+}
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(|| Mutex::new(State::default()));
+static READY: Condvar = Condvar::new();
+
+/// What a hotkey press amounts to.
+#[derive(Debug, PartialEq, Eq)]
+enum Press {
+    /// A repeat within [`DEBOUNCE`], or content already queued
+    Ignored,
+    /// The same content as the running job: pressing again means stop
+    CancelRunning,
+    /// New content, queued after dropping `dropped` queued jobs; the running
+    /// job is cancelled for it if `cancel_running`
+    Queued { dropped: usize, cancel_running: bool },
+}
+
+impl State {
+    fn press(&mut self, job: Job, queue: bool, now: Instant) -> Press {
+        let repeated = self
+            .last_press
+            .is_some_and(|(hash, at)| hash == job.hash && now.duration_since(at) < DEBOUNCE);
+        self.last_press = Some((job.hash, now));
+        if repeated || self.pending.iter().any(|pending| pending.hash == job.hash) {
+            return Press::Ignored;
+        }
+        if self.running == Some(job.hash) {
+            return Press::CancelRunning;
+        }
+
+        let mut dropped = 0;
+        if !queue {
+            dropped = self.pending.len();
+            self.pending.clear();
+        }
+        self.pending.push_back(job);
+        Press::Queued { dropped, cancel_running: !queue && self.running.is_some() }
+    }
+}
+
+/// Handle a hotkey press on `job`. The same content as the running job
+/// cancels it, a repeat of a queued job is dropped. New content replaces
+/// whatever is running or queued, unless `queue` is set, in which case it
+/// waits its turn.
+pub fn submit(job: Job, queue: bool) {
+    let mut state = STATE.lock().unwrap();
+    match state.press(job, queue, Instant::now()) {
+        Press::Ignored => logger::log("Jobs: repeated press ignored"),
+        Press::CancelRunning => {
+            if cancel::cancel() {
+                logger::log("Jobs: hotkey pressed again, cancelled running translation");
+            }
+        }
+        Press::Queued { dropped, cancel_running } => {
+            if dropped > 0 {
+                logger::log(&format!("Jobs: dropped {} stale queued job(s)", dropped));
+            }
+            if cancel_running && cancel::cancel() {
+                logger::log("Jobs: cancelled stale running translation");
+            }
+            ui::set_pending(state.pending.len());
+            READY.notify_one();
+        }
+    }
+}
+
+/// Marks a job as running until dropped, so presses on the same content
+/// cancel it rather than queue it again.
+pub struct Current {
+    _running: cancel::Running,
+}
+
+impl Drop for Current {
+    fn drop(&mut self) {
+        STATE.lock().unwrap().running = None;
+    }
+}
+
+/// Wait for the next job.
+pub fn next() -> (Job, Current) {
+    let mut state = STATE.lock().unwrap();
+    loop {
+        if let Some(job) = state.pending.pop_front() {
+            // Started under the lock so a cancel from `submit` cannot miss it
+            state.running = Some(job.hash);
+            ui::set_pending(state.pending.len());
+            return (job, Current { _running: cancel::Running::start() });
+        }
+        state = READY.wait(state).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(text: &str) -> Job {
+        Job::new(text.to_string(), None)
+    }
+
+    /// The worker has taken the queued job with `text`.
+    fn start(state: &mut State, text: &str) {
+        let hash = job(text).hash;
+        state.pending.retain(|pending| pending.hash != hash);
+        state.running = Some(hash);
+    }
+
+    #[test]
+    fn press_on_the_running_content_cancels_it() {
+        let mut state = State::default();
+        let t0 = Instant::now();
+        assert_eq!(state.press(job("hello"), false, t0), Press::Queued { dropped: 0, cancel_running: false });
+        start(&mut state, "hello");
+        assert_eq!(state.press(job("hello"), false, t0 + DEBOUNCE), Press::CancelRunning);
+        assert!(state.pending.is_empty());
+    }
+
+    #[test]
+    fn new_content_replaces_the_running_and_queued_jobs() {
+        let mut state = State::default();
+        let t0 = Instant::now();
+        state.press(job("one"), false, t0);
+        start(&mut state, "one");
+        state.press(job("two"), true, t0);
+        assert_eq!(state.press(job("three"), false, t0), Press::Queued { dropped: 1, cancel_running: true });
+        assert_eq!(state.pending.len(), 1);
+        assert_eq!(state.pending[0].text, "three");
+    }
+
+    #[test]
+    fn new_content_waits_its_turn_when_queueing() {
+        let mut state = State::default();
+        let t0 = Instant::now();
+        state.press(job("one"), true, t0);
+        start(&mut state, "one");
+        assert_eq!(state.press(job("two"), true, t0), Press::Queued { dropped: 0, cancel_running: false });
+        assert_eq!(state.press(job("three"), true, t0), Press::Queued { dropped: 0, cancel_running: false });
+        // A repeat of queued content does not queue it twice
+        assert_eq!(state.press(job("two"), true, t0 + DEBOUNCE), Press::Ignored);
+        assert_eq!(state.pending.len(), 2);
+    }
+
+    #[test]
+    fn presses_within_the_debounce_window_are_one() {
+        let mut state = State::default();
+        let t0 = Instant::now();
+        state.press(job("hello"), false, t0);
+        start(&mut state, "hello");
+        // Key repeat right after the first press does not cancel what it started
+        assert_eq!(state.press(job("hello"), false, t0 + DEBOUNCE / 2), Press::Ignored);
+        // Each press restarts the window
+        assert_eq!(state.press(job("hello"), false, t0 + DEBOUNCE), Press::Ignored);
+        assert_eq!(state.press(job("hello"), false, t0 + DEBOUNCE * 3), Press::CancelRunning);
+    }
+}
@@ -1,13 +1,20 @@
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
 
-use once_cell::sync::Lazy;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+mod cancel;
 mod config;
+mod http;
+mod jobs;
 mod ui;
 mod logger;
+mod providers;
+#[cfg(test)]
+mod replay;
+mod sse;
+mod usage;
 
 #[cfg(windows)]
 mod win_hotkey {
@@ -144,65 +151,6 @@ mod tray {
     }
 }
 
-static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
-    reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .build()
-        .expect("failed to build client")
-});
-
-#[derive(serde::Serialize)]
-struct ChatRequest<'a> {
-    model: &'a str,
-    messages: Vec<ChatMessage<'a>>,
-    temperature: f32,
-    max_tokens: Option<u32>,
-    stream: bool,
-}
-
-#[derive(serde::Serialize)]
-#[serde(untagged)]
-enum MessageContent<'a> {
-    Text(&'a str),
-    List(Vec<ContentPart<'a>>),
-}
-
-#[derive(serde::Serialize)]
-#[serde(tag = "type")]
-#[serde(rename_all = "snake_case")]
-enum ContentPart<'a> {
-    Text { text: &'a str },
-    ImageUrl { image_url: ImageUrl<'a> },
-}
-
-#[derive(serde::Serialize)]
-struct ImageUrl<'a> {
-    url: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    detail: Option<&'a str>,
-}
-
-#[derive(serde::Serialize)]
-struct ChatMessage<'a> {
-    role: &'a str,
-    content: MessageContent<'a>,
-}
-
-#[derive(serde::Deserialize)]
-struct ChatResponse {
-    choices: Vec<Choice>,
-}
-
-#[derive(serde::Deserialize)]
-struct Choice {
-    message: ChoiceMessage,
-}
-
-#[derive(serde::Deserialize)]
-struct ChoiceMessage {
-    content: String,
-}
-
 fn read_clipboard_string() -> Option<String> {
     #[cfg(windows)]
     {
@@ -365,289 +313,6 @@ pub(crate) fn write_clipboard_string(s: &str) -> bool {
     }
 }
 
-fn map_lang_to_code(lang: &str) -> &str {
-    match lang.to_lowercase().as_str() {
-        "chinese" | "zh" | "cn" | "zh-cn" => "zh-CN",
-        "english" | "en" | "us" | "uk" => "en",
-        "japanese" | "ja" | "jp" => "ja",
-        "korean" | "ko" | "kr" => "ko",
-        "french" | "fr" => "fr",
-        "spanish" | "es" => "es",
-        "german" | "de" => "de",
-        "russian" | "ru" => "ru",
-        "italian" | "it" => "it",
-        "portuguese" | "pt" => "pt",
-        "dutch" | "nl" => "nl",
-        "polish" | "pl" => "pl",
-        _ => "en", // Default to English if unknown
-    }
-}
-
-async fn translate_via_google_free<F>(
-    input: &str,
-    target_lang: &str,
-    mut on_chunk: F
-) -> anyhow::Result<String>
-where
-    F: FnMut(String),
-{
-    let target_code = map_lang_to_code(target_lang);
-    
-    // Google Translate Free API (gtx)
-    // url = "https://translate.googleapis.com/translate_a/single?client=gtx&sl=auto&tl={}&dt=t&q={}"
-    
-    let url = "https://translate.googleapis.com/translate_a/single";
-    let params = [
-        ("client", "gtx"),
-        ("sl", "auto"),
-        ("tl", target_code),
-        ("dt", "t"),
-        ("q", input),
-    ];
-    
-    let resp = CLIENT.get(url)
-        .query(&params)
-        .send()
-        .await?;
-        
-    if !resp.status().is_success() {
-        anyhow::bail!("Google API error: {}", resp.status());
-    }
-    
-    // The response is a nested JSON array. 
-    // [[["Translation", "Original", null, null, 1]], null, "en", ...]
-    // We need to iterate over the first array and join the first element of each inner array.
-    
-    let json: serde_json::Value = resp.json().await?;
-    
-    let mut full_text = String::new();
-    
-    if let Some(sentences) = json.as_array().and_then(|arr| arr.get(0)).and_then(|v| v.as_array()) {
-        for sentence in sentences {
-            if let Some(text) = sentence.as_array().and_then(|arr| arr.get(0)).and_then(|v| v.as_str()) {
-                full_text.push_str(text);
-            }
-        }
-    } else {
-        anyhow::bail!("Failed to parse Google Translate response");
-    }
-    
-    if full_text.is_empty() {
-        anyhow::bail!("Empty translation received");
-    }
-    
-    // Simulate streaming by just sending the whole text at once
-    on_chunk(full_text.clone());
-    
-    Ok(full_text)
-}
-
-async fn translate_via_openai_stream<F>(
-    input: &str, 
-    image_data: Option<ImageData>,
-    target_lang: &str, 
-    api_key: &str, 
-    model: &str, 
-    api_base: &str,
-    api_type: &str,
-    mut on_chunk: F
-) -> anyhow::Result<String>
-where
-    F: FnMut(String),
-{
-    use futures_util::StreamExt;
-    use base64::{Engine as _, engine::general_purpose};
-    
-    // Optimized prompt for gemma3:270m translation
-    let user_content = if target_lang.to_lowercase().contains("chinese") {
-        if image_data.is_some() {
-            "Translate the text in this image to Chinese, only output the translation, no other text".to_string()
-        } else {
-            format!("Translate '{}' to Chinese, only output the translation, no other text", input)
-        }
-    } else if target_lang.to_lowercase().contains("english") {
-        if image_data.is_some() {
-            "Translate the text in this image to English, only output the translation, no other text".to_string()
-        } else {
-            format!("Translate '{}' to English, only output the translation, no other text", input)
-        }
-    } else {
-        if image_data.is_some() {
-            format!("Translate the text in this image to {}, only output the translation, no other text", target_lang)
-        } else {
-            format!("Translate '{}' to {}, only output the translation, no other text", input, target_lang)
-        }
-    };
-
-    let mut messages = Vec::new();
-    if let Some(img) = image_data {
-        let b64 = general_purpose::STANDARD.encode(&img.bytes);
-        let data_url = format!("data:{};base64,{}", img.mime_type, b64);
-        messages.push(ChatMessage {
-            role: "user",
-            content: MessageContent::List(vec![
-                ContentPart::Text { text: &user_content },
-                ContentPart::ImageUrl {
-                    image_url: ImageUrl {
-                        url: data_url,
-                        detail: Some("high"),
-                    },
-                },
-            ]),
-        });
-    } else {
-        messages.push(ChatMessage {
-            role: "user",
-            content: MessageContent::Text(&user_content),
-        });
-    }
-
-    let req = ChatRequest {
-        model,
-        messages,
-        temperature: 0.1,
-        max_tokens: Some(1024),
-        stream: true,
-    };
-
-    // Build request with appropriate authentication and endpoint
-    let (endpoint, request_body) = if api_type == "ollama" {
-        // Use native Ollama API format
-        let ollama_endpoint = format!("{}/api/generate", api_base);
-        
-        let mut ollama_req = serde_json::json!({
-            "model": model,
-            "prompt": user_content,
-            "stream": true
-        });
-
-        if let MessageContent::List(ref list) = req.messages[0].content {
-            for part in list {
-                if let ContentPart::ImageUrl { image_url } = part {
-                    // Extract base64 from data URL
-                    if let Some(b64) = image_url.url.split(',').last() {
-                        ollama_req["images"] = serde_json::json!([b64]);
-                    }
-                }
-            }
-        }
-
-        (ollama_endpoint, ollama_req)
-    } else {
-        // Use OpenAI-compatible API format
-        let openai_endpoint = format!("{}/chat/completions", api_base);
-        let openai_req = serde_json::to_value(&req).unwrap();
-        (openai_endpoint, openai_req)
-    };
-    
-    let mut request_builder = CLIENT.post(&endpoint).json(&request_body);
-    
-    // Add authentication based on API type
-    if api_type != "ollama" && !api_key.is_empty() {
-        request_builder = request_builder.bearer_auth(api_key);
-    }
-    
-    // Debug logging for Ollama requests
-    if api_type == "ollama" {
-        logger::log(&format!("Ollama native API request to: {}", endpoint));
-        logger::log(&format!("Ollama model: {}", model));
-        logger::log(&format!("User prompt: {}", user_content));
-    }
-    
-    let resp = request_builder.send().await?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        
-        // Handle common Ollama error for non-vision models
-        if api_type == "ollama" && status == 500 && text.contains("missing data required for image input") {
-            anyhow::bail!("Ollama error: The model '{}' does not support images. Please use a vision model like 'llava'.", model);
-        }
-        
-        anyhow::bail!("API error {}: {}", status, text);
-    }
-
-    let mut stream = resp.bytes_stream();
-    let mut full_text = String::new();
-    // Keep raw bytes so we never corrupt multi-byte UTF-8 characters (e.g.
-    // Chinese) that happen to be split across two HTTP chunks. Only convert
-    // to String once we have a complete UTF-8 sequence up to the last '\n'.
-    let mut raw_buffer: Vec<u8> = Vec::new();
-    let mut done = false;
-
-    while !done {
-        match stream.next().await {
-            Some(chunk_result) => raw_buffer.extend_from_slice(&chunk_result?),
-            None => {
-                // Stream ended: flush whatever is still in the buffer (handles
-                // servers that omit the trailing '\n' on the last chunk).
-                done = true;
-                if !raw_buffer.is_empty() {
-                    raw_buffer.push(b'\n');
-                }
-            }
-        }
-
-        // Only process complete lines (up to the last '\n').  Anything after
-        // the last '\n' is a partial line — leave it in the buffer so the next
-        // chunk can complete it.  This is the core fix for missing words when
-        // an HTTP chunk boundary falls in the middle of a "data: {...}" line.
-        let last_newline = raw_buffer.iter().rposition(|&b| b == b'\n');
-        let process_up_to = last_newline.map(|p| p + 1).unwrap_or(0);
-        if process_up_to == 0 {
-            continue; // No complete line yet
-        }
-
-        // Safe UTF-8 conversion: everything up to the split point is a
-        // sequence of complete SSE lines, so it must be valid UTF-8.
-        let to_process = String::from_utf8_lossy(&raw_buffer[..process_up_to]).into_owned();
-        raw_buffer = raw_buffer[process_up_to..].to_vec();
-
-        if api_type == "ollama" {
-            // Native Ollama API: each line is a standalone JSON object.
-            for line in to_process.lines() {
-                let line = line.trim();
-                if line.is_empty() { continue; }
-                if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(line) {
-                    if parsed["done"].as_bool() == Some(true) {
-                        done = true;
-                    }
-                    if let Some(content) = parsed["response"].as_str() {
-                        full_text.push_str(content);
-                        on_chunk(content.to_string());
-                    }
-                }
-            }
-        } else {
-            // OpenAI-compatible SSE format.
-            'outer: for line in to_process.lines() {
-                let line = line.trim();
-                if !line.starts_with("data: ") { continue; }
-
-                let json_str = &line[6..];
-                if json_str == "[DONE]" {
-                    done = true;
-                    break 'outer;
-                }
-
-                if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str) {
-                    if let Some(content) = parsed["choices"][0]["delta"]["content"].as_str() {
-                        full_text.push_str(content);
-                        on_chunk(content.to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    if full_text.is_empty() {
-        anyhow::bail!("Empty response from OpenAI");
-    }
-
-    Ok(full_text)
-}
-
 fn toast(title: &str, body: &str) {
     #[cfg(windows)]
     {
@@ -692,6 +357,9 @@ fn main() {
     if let Ok(v) = std::env::var("OPENAI_MODEL") { if !v.is_empty() { cfg.openai_model = v; } }
     if let Ok(v) = std::env::var("TARGET_LANG") { if !v.is_empty() { cfg.target_lang = v; } }
     
+    // Backend threads talk to the window from here on
+    ui::connect();
+
     // Channels
     let (hotkey_tx, hotkey_rx) = mpsc::channel::<()>();
     let (tray_tx, tray_rx) = mpsc::channel::<tray::TrayAction>();
@@ -794,85 +462,221 @@ fn main() {
         });
     }
 
+    // Background: capture the clipboard on each press and queue it as a job
+    {
+        let cfg = Arc::clone(&cfg);
+        thread::spawn(move || {
+            while let Ok(()) = hotkey_rx.recv() {
+                // Small delay to let the source application release the clipboard
+                // Especially important when triggered via hotkey
+                thread::sleep(Duration::from_millis(150));
+
+                let image = read_clipboard_image();
+                let text = read_clipboard_string();
+
+                if image.is_none() && text.as_ref().is_none_or(|s| s.trim().is_empty()) {
+                    toast("Echo", "Clipboard is empty.");
+                    logger::log("Hotkey: Clipboard empty");
+                    continue;
+                }
+                let queue = cfg.lock().unwrap().queue_jobs;
+                jobs::submit(jobs::Job::new(text.unwrap_or_default(), image), queue);
+            }
+        });
+    }
+
     // Background: hotkey translation worker
     {
         let cfg = Arc::clone(&cfg);
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().expect("tokio rt");
-            while let Ok(()) = hotkey_rx.recv() {
-                let (api_key, model, target_lang, api_base, api_type) = {
-                    let c = cfg.lock().unwrap().clone();
-                    (c.openai_api_key, c.openai_model, c.target_lang, c.api_base, c.api_type)
+            loop {
+                let (job, _current) = jobs::next();
+                let c = cfg.lock().unwrap().clone();
+                let _tally = usage::Tally::start(&c.prices, &usage::default_path());
+                let chain = providers::chain_from_config(&c);
+                let compare = if c.compare_mode && !c.compare_providers.is_empty() {
+                    Some(providers::compare_from_config(&c))
+                } else {
+                    None
                 };
+                let info = chain[0].info();
                 
                 // Check if API key is required (not needed for Ollama or Google Free)
-                if api_type != "ollama" && api_type != "google_free" && api_key.is_empty() {
+                if compare.as_ref().unwrap_or(&chain).iter().all(|endpoint| endpoint.missing_key) {
                     toast("Echo", "Missing API key. Configure in settings.");
                     logger::log("Hotkey: Missing API key");
                 } else {
-                    // Small delay to let the source application release the clipboard
-                    // Especially important when triggered via hotkey
-                    thread::sleep(Duration::from_millis(150));
-
-                    let image = read_clipboard_image();
-                    let text = read_clipboard_string();
+                    // Show window immediately with loading indicator
+                    ui::set_translating(true);
+                    toast("Echo", "Translating...");
                     
-                    if image.is_none() && text.as_ref().map_or(true, |s| s.trim().is_empty()) {
-                        toast("Echo", "Clipboard is empty.");
-                        logger::log("Hotkey: Clipboard empty");
-                    } else {
-                        // Show window immediately with loading indicator
-                        ui::set_translating(true);
-                        toast("Echo", "Translating...");
-                        
-                        let input_text = text.unwrap_or_default();
-                        let has_image = image.is_some();
-                        
-                        logger::log(&format!("Translating (image: {}, text len: {}) with {} ({}) to {}", 
-                            has_image, input_text.len(), model, api_type, target_lang));
-                        
-                        let res = rt.block_on(async move {
-                            // Clear text and start fresh
-                            ui::show_output_text(String::new());
-                            
-                            if api_type == "google_free" {
-                                if has_image {
-                                    // Google Free doesn't support images
-                                    let msg = "Google Translate (Free) does not support image translation.".to_string();
-                                    ui::append_text(msg.clone());
-                                    Ok(msg)
-                                } else {
-                                    translate_via_google_free(&input_text, &target_lang, |chunk| {
-                                        ui::append_text(chunk);
-                                    }).await
+                    let jobs::Job { text: input_text, image, .. } = job;
+                    let has_image = image.is_some();
+                    
+                    // Words and short phrases are looked up offline before bothering a
+                    // backend, then single words in the backend's dictionary if it has one
+                    if !has_image && providers::is_phrase_lookup(&input_text) {
+                        let word = input_text.split_whitespace().collect::<Vec<_>>().join(" ");
+                        let word = word.as_str();
+                        let mut entries = if c.stardict_dirs.is_empty() {
+                            Vec::new()
+                        } else {
+                            providers::stardict::lookup(&c.stardict_dirs, word)
+                        };
+                        if entries.is_empty() && providers::is_word_lookup(word) && info.capabilities.dictionary && !chain[0].missing_key {
+                            ui::set_provider(info.short_label);
+                            match rt.block_on(cancel::run(chain[0].provider.lookup(word, &c.target_lang))) {
+                                None => {
+                                    ui::mark_cancelled();
+                                    logger::log("Dictionary: lookup cancelled");
+                                    continue;
                                 }
+                                Some(Ok(found)) => entries = found,
+                                // Plain translation still has a chance
+                                Some(Err(e)) => logger::log(&format!("Dictionary: {} lookup failed: {}", info.id, e)),
+                            }
+                        }
+                        if !entries.is_empty() {
+                            logger::log(&format!("Dictionary: {} entries for '{}'", entries.len(), word));
+                            ui::set_source_lang(entries[0].source_lang.clone());
+                            // The main translation still goes to the clipboard, as for any text
+                            if write_clipboard_string(&entries[0].translation) {
+                                toast("Echo", &format!("Copied to clipboard! (via {})", entries[0].source));
                             } else {
-                                translate_via_openai_stream(&input_text, image, &target_lang, &api_key, &model, &api_base, &api_type, |chunk| {
-                                    // Stream each chunk to the UI as it arrives
-                                    ui::append_text(chunk);
-                                }).await
+                                toast("Echo", &format!("Found in {} (copy failed)", entries[0].source));
+                                logger::log("Dictionary: failed to write clipboard");
                             }
-                        });
+                            ui::show_entries(entries);
+                            continue;
+                        }
+                    }
+
+                    if let Some(compare) = compare {
+                        logger::log(&format!("Comparing {} providers (image: {}, text len: {}) to {}",
+                            compare.len(), has_image, input_text.len(), c.target_lang));
+
+                        let req = providers::TranslationRequest { text: input_text, image, target_lang: c.target_lang, max_tokens: c.max_tokens };
+                        let results = rt.block_on(cancel::run(async {
+                            ui::show_output_text(String::new());
+                            ui::start_compare(compare.iter().map(|endpoint| endpoint.label()).collect());
+                            providers::translate_side_by_side(&compare, &req, &|i, chunk| {
+                                ui::append_column(i, chunk);
+                            }, &|i, res| {
+                                if let Err(e) = res {
+                                    logger::log(&format!("Compare: {} failed: {}", compare[i].label(), e));
+                                }
+                                ui::finish_column(i, res.as_ref().err().map(|e| e.to_string()));
+                            }).await
+                        }));
+
+                        let Some(results) = results else {
+                            ui::mark_cancelled();
+                            toast("Echo", "Translation cancelled");
+                            logger::log("Compare: cancelled");
+                            continue;
+                        };
+                        ui::set_translating(false);
+                        let succeeded = results.iter().filter(|res| res.is_ok()).count();
+                        let truncated = results.iter().filter(|res| res.as_ref().is_ok_and(|t| t.truncated)).count();
+                        if truncated > 0 {
+                            toast("Echo", &format!("{} of {} providers done, {} cut off at max_tokens. Click a column to copy.", succeeded, results.len(), truncated));
+                        } else {
+                            toast("Echo", &format!("{} of {} providers done. Click a column to copy.", succeeded, results.len()));
+                        }
+                        continue;
+                    }
+
+                    logger::log(&format!("Translating (image: {}, text len: {}) with {} ({}) to {}", 
+                        has_image, input_text.len(), c.openai_model, info.id, c.target_lang));
+
+                    // Long texts go in segments that fit the answer into max_tokens
+                    let segments = if has_image || info.capabilities.long_text {
+                        Vec::new()
+                    } else {
+                        providers::segment::split(&input_text, c.segment_tokens as usize)
+                    };
+                    
+                    let res = rt.block_on(cancel::run(async move {
+                        // Clear text and start fresh
+                        ui::show_output_text(String::new());
                         
-                        match res {
-                            Ok(out) => {
-                                ui::set_translating(false);
-                                let ok = write_clipboard_string(&out);
-                                if ok {
-                                    toast("Echo", "Copied to clipboard!");
-                                    logger::log("Translation success; copied to clipboard");
-                                } else {
-                                    toast("Echo", "Translated (copy failed)");
-                                    logger::log("Translation success; failed to write clipboard");
+                        if has_image && !chain.iter().any(|endpoint| endpoint.info().capabilities.image) {
+                            let msg = format!("{} does not support image translation.", info.label);
+                            ui::append_text(msg.clone());
+                            Ok((msg.into(), info))
+                        } else if segments.len() > 1 {
+                            logger::log(&format!("Translating in {} segments, {} at a time", segments.len(), c.segment_concurrency.max(1)));
+                            let req = providers::TranslationRequest { text: input_text, image, target_lang: c.target_lang, max_tokens: c.max_tokens };
+                            let res = providers::segment::translate_segments(&chain, &req, &segments, c.segment_concurrency as usize, &providers::segment::SegmentOutput {
+                                on_attempt: &|next| ui::set_provider(next.short_label),
+                                on_chunk: &ui::append_text,
+                                on_reset: &ui::show_output_text,
+                                on_progress: &|done, total| ui::set_progress(Some((done, total))),
+                                on_wait: &|secs| ui::set_status((secs > 0).then(|| format!("Retrying in {}s", secs))),
+                            }).await;
+                            ui::set_progress(None);
+                            res
+                        } else {
+                            let req = providers::TranslationRequest { text: input_text, image, target_lang: c.target_lang, max_tokens: c.max_tokens };
+                            let mut attempts = 0;
+                            providers::translate_with_fallback(&chain, &req, &mut |next| {
+                                // Drop partial output from the backend that just failed
+                                if attempts > 0 {
+                                    ui::show_output_text(String::new());
+                                    toast("Echo", &format!("Retrying with {}...", next.short_label));
                                 }
+                                attempts += 1;
+                                ui::set_provider(next.short_label);
+                            }, &mut |chunk| {
+                                // Stream each chunk to the UI as it arrives
+                                ui::append_text(chunk);
+                            }, &mut |secs| {
+                                ui::set_status((secs > 0).then(|| format!("Retrying in {}s", secs)));
+                            }).await
+                        }
+                    }));
+                    
+                    // Blocking work that stopped for the cancel reports it as an error
+                    let res = res.filter(|res| !res.as_ref().is_err_and(|e| e.chain().any(|c| c.is::<cancel::Cancelled>())));
+                    match res {
+                        None => {
+                            // Keep whatever was streamed so far
+                            ui::mark_cancelled();
+                            toast("Echo", "Translation cancelled");
+                            logger::log("Translation cancelled");
+                        }
+                        Some(Ok((out, used))) => {
+                            ui::set_translating(false);
+                            // Compared columns may disagree, so only a single translation says
+                            ui::set_source_lang(out.source_lang.clone());
+                            if out.untranslated {
+                                logger::log("Translation: text is already in the target language");
+                                ui::mark_untranslated();
                             }
-                            Err(e) => {
-                                ui::set_translating(false);
-                                toast("Echo", &format!("Error: {}", e));
-                                logger::log(&format!("Translation error: {}", e));
-                                ui::show_output_text(format!("❌ Error: {}", e));
+                            let ok = write_clipboard_string(&out.text);
+                            // The answer hit max_tokens; the end of the text is missing
+                            let cut_off = if out.truncated {
+                                ui::set_status(Some(format!("Cut off at max_tokens ({})", c.max_tokens)));
+                                logger::log(&format!("Translation via {} cut off at max_tokens ({})", used.id, c.max_tokens));
+                                ", cut off at max_tokens"
+                            } else {
+                                ""
+                            };
+                            if ok {
+                                toast("Echo", &format!("Copied to clipboard! (via {}{})", used.short_label, cut_off));
+                                logger::log(&format!("Translation success via {}; copied to clipboard", used.id));
+                            } else {
+                                toast("Echo", &format!("Translated via {}{} (copy failed)", used.short_label, cut_off));
+                                logger::log("Translation success; failed to write clipboard");
                             }
                         }
+                        Some(Err(e)) => {
+                            ui::set_translating(false);
+                            toast("Echo", &format!("Error: {}", e));
+                            logger::log(&format!("Translation error: {}", e));
+                            ui::show_output_text(format!("❌ Error: {}", e));
+                        }
                     }
                 }
             }
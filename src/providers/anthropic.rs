@@ -0,0 +1,156 @@
+//! Anthropic Messages API (`/v1/messages`).
+
+use super::{ApiError, ApiKey, Capabilities, EmptyResponse, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use futures_util::future::BoxFuture;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "anthropic",
+    aliases: &["claude"],
+    label: "Anthropic (Claude)",
+    short_label: "Claude",
+    default_api_base: "https://api.anthropic.com/v1",
+    default_model: "claude-3-5-haiku-latest",
+    api_key: ApiKey::Required,
+    api_key_hint: "sk-ant-...",
+    capabilities: Capabilities { text: true, image: true, detect_language: false, list_languages: false, long_text: false, dictionary: false },
+    models: ModelList::Fixed(&[
+        ("claude-3-5-haiku-latest", "Claude 3.5 Haiku"),
+        ("claude-3-5-sonnet-latest", "Claude 3.5 Sonnet"),
+    ]),
+    build: |cfg: &Config| {
+        Box::new(AnthropicProvider {
+            api_key: cfg.openai_api_key.clone(),
+            model: cfg.openai_model.clone(),
+            api_base: cfg.api_base.clone(),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct AnthropicProvider {
+    api_key: String,
+    model: String,
+    api_base: String,
+    http: ClientOptions,
+}
+
+impl TranslationProvider for AnthropicProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            let request_builder = crate::http::client(&self.http)?
+                .post(format!("{}/messages", self.api_base))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION);
+            translate_via_anthropic_stream(
+                req,
+                &self.model,
+                request_builder,
+                &self.http,
+                on_chunk,
+            )
+            .await
+        })
+    }
+}
+
+async fn translate_via_anthropic_stream(
+    req: &TranslationRequest,
+    model: &str,
+    request_builder: reqwest::RequestBuilder,
+    http: &ClientOptions,
+    on_chunk: &mut (dyn FnMut(String) + Send),
+) -> anyhow::Result<Translation> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let user_content = super::build_prompt(&req.text, req.image.is_some(), &req.target_lang);
+
+    let mut content = Vec::new();
+    if let Some(img) = &req.image {
+        // Images go before the instruction, as recommended by the Messages API docs
+        content.push(serde_json::json!({
+            "type": "image",
+            "source": {
+                "type": "base64",
+                "media_type": img.mime_type,
+                "data": general_purpose::STANDARD.encode(&img.bytes),
+            }
+        }));
+    }
+    content.push(serde_json::json!({ "type": "text", "text": user_content }));
+
+    let body = serde_json::json!({
+        "model": model,
+        "max_tokens": req.max_tokens,
+        "temperature": 0.1,
+        "stream": true,
+        "messages": [{ "role": "user", "content": content }],
+    });
+
+    let resp = crate::http::send(http.apply(request_builder)?.json(&body), http.timeouts()).await?;
+
+    if !resp.status().is_success() {
+        return Err(ApiError::from_response(resp).await.into());
+    }
+
+    let mut full_text = String::new();
+    let mut finished = false;
+    let mut truncated = false;
+    let mut input_tokens = 0;
+    crate::sse::for_each_event(resp, |event| {
+        if event.event == "error" {
+            anyhow::bail!("Anthropic stream error: {}", super::error_event_message(&event.data));
+        }
+        let parsed = super::parse_event_json(&event.data)?;
+        if let Some(e) = super::stream_error(&parsed) {
+            anyhow::bail!("Anthropic stream error: {}", e);
+        }
+        match event.event.as_str() {
+            "message_start" => {
+                input_tokens = parsed["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                Ok(false)
+            }
+            "message_delta" => {
+                truncated = parsed["delta"]["stop_reason"] == "max_tokens";
+                // The output count is cumulative and final in the last delta
+                if let Some(output_tokens) = parsed["usage"]["output_tokens"].as_u64() {
+                    crate::usage::record(model, input_tokens, output_tokens);
+                }
+                Ok(false)
+            }
+            "content_block_delta" => {
+                if let Some(text) = parsed["delta"]["text"].as_str() {
+                    full_text.push_str(text);
+                    on_chunk(text.to_string());
+                }
+                Ok(false)
+            }
+            "message_stop" => {
+                finished = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    })
+    .await?;
+
+    if full_text.is_empty() {
+        return Err(EmptyResponse("Anthropic").into());
+    }
+    if !finished {
+        anyhow::bail!("The stream ended before the translation was complete");
+    }
+
+    Ok(Translation { truncated, ..full_text.into() })
+}
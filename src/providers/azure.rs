@@ -0,0 +1,123 @@
+//! OpenAI models deployed in an Azure OpenAI resource.
+
+use super::{ApiKey, Capabilities, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use futures_util::future::BoxFuture;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "azure_openai",
+    aliases: &["azure"],
+    label: "Azure OpenAI",
+    short_label: "Azure",
+    // Every resource has its own endpoint, so there is nothing sensible to default to
+    default_api_base: "",
+    default_model: "",
+    api_key: ApiKey::Required,
+    api_key_hint: "Key 1 or Key 2 from the Azure portal",
+    capabilities: Capabilities { text: true, image: true, detect_language: false, list_languages: false, long_text: false, dictionary: false },
+    models: ModelList::None,
+    build: |cfg: &Config| {
+        Box::new(AzureOpenAiProvider {
+            api_key: cfg.openai_api_key.clone(),
+            api_base: cfg.api_base.trim_end_matches('/').to_string(),
+            deployment: cfg.azure_deployment.clone(),
+            api_version: cfg.azure_api_version.clone(),
+            include_usage: cfg.stream_usage.unwrap_or_else(|| reports_usage(&cfg.azure_api_version)),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct AzureOpenAiProvider {
+    api_key: String,
+    api_base: String,
+    deployment: String,
+    api_version: String,
+    /// Ask for token usage; `stream_usage` in the config, else [`reports_usage`]
+    include_usage: bool,
+    http: ClientOptions,
+}
+
+impl TranslationProvider for AzureOpenAiProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            // The base URL left over from another backend is never an Azure resource
+            if self.api_base.is_empty() || super::is_default_api_base(&self.api_base) {
+                anyhow::bail!("Set the API Base URL to your Azure resource, e.g. https://my-resource.openai.azure.com");
+            }
+            if self.deployment.is_empty() {
+                anyhow::bail!("Set the Azure deployment name in settings");
+            }
+
+            let endpoint = format!("{}/openai/deployments/{}/chat/completions", self.api_base, self.deployment);
+            let request_builder = crate::http::client(&self.http)?
+                .post(&endpoint)
+                .query(&[("api-version", self.api_version.as_str())])
+                .header("api-key", &self.api_key);
+
+            // Azure routes by deployment; the model field is ignored but must be present
+            super::openai::translate_via_openai_stream(
+                req,
+                &self.deployment,
+                request_builder,
+                &self.http,
+                self.include_usage,
+                on_chunk,
+            )
+            .await
+        })
+    }
+}
+
+/// Whether `api_version` takes `stream_options`; earlier ones reject the
+/// request. Versions are dates, so they sort as strings.
+fn reports_usage(api_version: &str) -> bool {
+    api_version >= "2024-09-01"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay;
+
+    async fn sent_body(api_version: &str) -> serde_json::Value {
+        let server = replay::serve("azure_stream", "https://my-resource.openai.azure.com").await;
+        let provider = (INFO.build)(&Config {
+            api_type: INFO.id.to_string(),
+            api_base: server.url.clone(),
+            openai_api_key: "azure-key".to_string(),
+            azure_deployment: "gpt-4o-mini".to_string(),
+            azure_api_version: api_version.to_string(),
+            ..Config::default()
+        });
+        let req = TranslationRequest { text: "Hello, world!".to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 };
+        provider.translate(&req, &mut |_| {}).await.unwrap();
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.target, format!("/openai/deployments/gpt-4o-mini/chat/completions?api-version={}", api_version));
+        assert_eq!(sent.header("api-key"), Some("azure-key"));
+        sent.json()
+    }
+
+    #[tokio::test]
+    async fn default_api_version_is_not_asked_for_usage() {
+        assert_eq!(Config::default().azure_api_version, "2024-06-01");
+        assert!(sent_body("2024-06-01").await.get("stream_options").is_none());
+    }
+
+    #[tokio::test]
+    async fn newer_api_versions_report_usage() {
+        assert!(!reports_usage("2024-08-01-preview"));
+        assert!(reports_usage("2024-09-01-preview"));
+        assert_eq!(sent_body("2024-10-21").await["stream_options"]["include_usage"], true);
+    }
+}
@@ -0,0 +1,246 @@
+//! DeepL API (`/v2/translate`), free and pro plans.
+
+use super::{ApiError, ApiKey, Capabilities, EmptyResponse, Language, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use futures_util::future::BoxFuture;
+
+const FREE_HOST: &str = "https://api-free.deepl.com";
+const PRO_HOST: &str = "https://api.deepl.com";
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "deepl",
+    aliases: &[],
+    label: "DeepL",
+    short_label: "DeepL",
+    default_api_base: FREE_HOST,
+    default_model: "",
+    api_key: ApiKey::Required,
+    api_key_hint: "xxxxxxxx-xxxx-...:fx",
+    capabilities: Capabilities { text: true, image: false, detect_language: false, list_languages: true, long_text: false, dictionary: false },
+    models: ModelList::None,
+    build: |cfg: &Config| {
+        Box::new(DeepLProvider {
+            api_key: cfg.openai_api_key.clone(),
+            api_base: cfg.api_base.clone(),
+            formality: cfg.deepl_formality.clone(),
+            glossary_id: cfg.deepl_glossary_id.clone(),
+            source_lang: cfg.deepl_source_lang.clone(),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct DeepLProvider {
+    api_key: String,
+    api_base: String,
+    formality: String,
+    glossary_id: String,
+    source_lang: String,
+    http: ClientOptions,
+}
+
+impl DeepLProvider {
+    /// Free-plan keys end in ":fx" and only work against the free host, so
+    /// pick the right official host from the key unless a custom one is set.
+    fn host(&self) -> &str {
+        let base = self.api_base.trim_end_matches('/');
+        if base.is_empty() || base == FREE_HOST || base == PRO_HOST {
+            if self.api_key.ends_with(":fx") { FREE_HOST } else { PRO_HOST }
+        } else {
+            base
+        }
+    }
+
+    async fn translate_text(&self, input: &str, target_lang: &str) -> anyhow::Result<String> {
+        let mut body = serde_json::json!({
+            "text": [input],
+            "target_lang": deepl_lang_code(target_lang),
+        });
+        if !self.source_lang.is_empty() {
+            body["source_lang"] = deepl_source_code(&self.source_lang).into();
+        }
+        if !self.formality.is_empty() {
+            // Sent as configured: "more"/"less" are rejected for target
+            // languages without formality support, "prefer_" ones fall back
+            body["formality"] = self.formality.clone().into();
+        }
+        if !self.glossary_id.is_empty() {
+            if self.source_lang.is_empty() {
+                anyhow::bail!("DeepL glossaries require a source language; set deepl_source_lang");
+            }
+            body["glossary_id"] = self.glossary_id.clone().into();
+        }
+
+        let endpoint = format!("{}/v2/translate", self.host());
+        let request = crate::http::client(&self.http)?
+            .post(&endpoint)
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key));
+        let request = self.http.apply(request)?.json(&body);
+        let resp = crate::http::send(request, self.http.timeouts()).await?;
+
+        if !resp.status().is_success() {
+            let err = ApiError::from_response(resp).await;
+            if err.status.as_u16() == 456 {
+                let msg = format!("DeepL quota exceeded: {}", err.body);
+                return Err(anyhow::Error::new(err).context(msg));
+            }
+            return Err(err.into());
+        }
+
+        let json: serde_json::Value = crate::http::json(resp).await?;
+        let text = json["translations"][0]["text"].as_str().unwrap_or_default().to_string();
+        if text.is_empty() {
+            return Err(EmptyResponse("DeepL").into());
+        }
+        Ok(text)
+    }
+}
+
+impl TranslationProvider for DeepLProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            let text = self.translate_text(&req.text, &req.target_lang).await?;
+            // DeepL does not stream; deliver the whole result at once
+            on_chunk(text.clone());
+            Ok(text.into())
+        })
+    }
+
+    fn languages(&self) -> BoxFuture<'_, anyhow::Result<Vec<Language>>> {
+        Box::pin(async move {
+            let request = crate::http::client(&self.http)?
+                .get(format!("{}/v2/languages", self.host()))
+                .query(&[("type", "target")])
+                .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key));
+            let resp = crate::http::send(self.http.apply(request)?, self.http.timeouts()).await?;
+            if !resp.status().is_success() {
+                return Err(ApiError::from_response(resp).await.into());
+            }
+
+            // [{"language": "EN-US", "name": "English (American)", "supports_formality": false}, ...]
+            let json: serde_json::Value = crate::http::json(resp).await?;
+            let languages = json
+                .as_array()
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|l| {
+                            Some(Language {
+                                code: l["language"].as_str()?.to_string(),
+                                name: l["name"].as_str()?.to_string(),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Ok(languages)
+        })
+    }
+}
+
+/// DeepL target language code for a `Config.target_lang` value.
+/// DeepL wants upper-case codes and an explicit variant for English,
+/// Portuguese and Chinese.
+fn deepl_lang_code(lang: &str) -> String {
+    match super::map_lang_to_code(lang) {
+        "zh-CN" => "ZH-HANS".to_string(),
+        "zh-TW" => "ZH-HANT".to_string(),
+        "en" => "EN-US".to_string(),
+        "pt" => "PT-BR".to_string(),
+        "no" => "NB".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+/// Source languages never take a variant suffix.
+fn deepl_source_code(lang: &str) -> String {
+    match super::map_lang_to_code(lang) {
+        "no" => "NB".to_string(),
+        other => other.split('-').next().unwrap_or(other).to_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay;
+
+    fn provider(api_base: &str) -> Box<dyn TranslationProvider> {
+        let cfg = Config {
+            api_type: INFO.id.to_string(),
+            api_base: api_base.to_string(),
+            openai_api_key: std::env::var("DEEPL_API_KEY").unwrap_or_else(|_| "deepl-test:fx".to_string()),
+            deepl_formality: "prefer_less".to_string(),
+            ..Config::default()
+        };
+        (INFO.build)(&cfg)
+    }
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest { text: text.to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 }
+    }
+
+    #[tokio::test]
+    async fn text_is_translated() {
+        let server = replay::serve("deepl_translate", FREE_HOST).await;
+        let mut chunks = Vec::new();
+        let text = provider(&server.url).translate(&request("Hello, world!"), &mut |c| chunks.push(c)).await.unwrap().text;
+        assert_eq!(text, "你好，世界！");
+        assert_eq!(chunks, [text]);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.target, "/v2/translate");
+        assert!(sent.header("authorization").is_some_and(|v| v.starts_with("DeepL-Auth-Key ")));
+        let body = sent.json();
+        assert_eq!(body["text"][0], "Hello, world!");
+        assert_eq!(body["target_lang"], "ZH-HANS");
+        assert_eq!(body["formality"], "prefer_less");
+        assert!(body.get("source_lang").is_none());
+    }
+
+    #[tokio::test]
+    async fn rejected_key_is_an_api_error() {
+        let server = replay::serve("deepl_forbidden", FREE_HOST).await;
+        let err = provider(&server.url).translate(&request("Hello"), &mut |_| {}).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ApiError>().expect("an ApiError").status, 403);
+        assert!(!super::super::should_fall_back(&err));
+    }
+
+    #[tokio::test]
+    async fn exhausted_quota_falls_back() {
+        let server = replay::serve("deepl_quota_exceeded", FREE_HOST).await;
+        let err = provider(&server.url).translate(&request("Hello"), &mut |_| {}).await.unwrap_err();
+        assert!(err.to_string().starts_with("DeepL quota exceeded"), "{}", err);
+        assert_eq!(err.downcast_ref::<ApiError>().expect("an ApiError").status, 456);
+        // Another backend may still have quota left
+        assert!(super::super::should_fall_back(&err));
+    }
+
+    #[tokio::test]
+    async fn target_languages_are_listed() {
+        let server = replay::serve("deepl_languages", FREE_HOST).await;
+        let languages = provider(&server.url).languages().await.unwrap();
+        let codes: Vec<&str> = languages.iter().map(|l| l.code.as_str()).collect();
+        assert_eq!(codes, ["DE", "EN-GB", "EN-US", "ZH-HANS"]);
+        assert_eq!(languages[1].name, "English (British)");
+        assert_eq!(server.requests()[0].target, "/v2/languages?type=target");
+    }
+
+    #[test]
+    fn language_codes() {
+        assert_eq!(deepl_lang_code("Chinese"), "ZH-HANS");
+        assert_eq!(deepl_lang_code("Traditional Chinese"), "ZH-HANT");
+        assert_eq!(deepl_lang_code("English"), "EN-US");
+        assert_eq!(deepl_lang_code("ja"), "JA");
+        assert_eq!(deepl_source_code("zh-TW"), "ZH");
+        assert_eq!(deepl_source_code("Norwegian"), "NB");
+    }
+}
@@ -0,0 +1,256 @@
+//! Google Gemini through its native REST API (`streamGenerateContent`).
+
+use super::{ApiError, ApiKey, Capabilities, EmptyResponse, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use futures_util::future::BoxFuture;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "gemini",
+    aliases: &[],
+    label: "Google Gemini",
+    short_label: "Gemini",
+    default_api_base: "https://generativelanguage.googleapis.com/v1beta",
+    default_model: "gemini-2.0-flash",
+    api_key: ApiKey::Required,
+    api_key_hint: "AIza...",
+    capabilities: Capabilities { text: true, image: true, detect_language: false, list_languages: false, long_text: false, dictionary: false },
+    models: ModelList::Fixed(&[
+        ("gemini-2.0-flash", "Gemini 2.0 Flash"),
+        ("gemini-1.5-flash", "Gemini 1.5 Flash"),
+        ("gemini-1.5-pro", "Gemini 1.5 Pro"),
+    ]),
+    build: |cfg: &Config| {
+        Box::new(GeminiProvider {
+            api_key: cfg.openai_api_key.clone(),
+            model: cfg.openai_model.clone(),
+            api_base: cfg.api_base.clone(),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct GeminiProvider {
+    api_key: String,
+    model: String,
+    api_base: String,
+    http: ClientOptions,
+}
+
+impl TranslationProvider for GeminiProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            let request_builder = crate::http::client(&self.http)?
+                .post(format!("{}/models/{}:streamGenerateContent", self.api_base, self.model))
+                .query(&[("alt", "sse")])
+                // Not in the URL, which ends up in error messages and the log
+                .header("x-goog-api-key", &self.api_key);
+            translate_via_gemini_stream(req, &self.model, request_builder, &self.http, on_chunk).await
+        })
+    }
+}
+
+async fn translate_via_gemini_stream(
+    req: &TranslationRequest,
+    model: &str,
+    request_builder: reqwest::RequestBuilder,
+    http: &ClientOptions,
+    on_chunk: &mut (dyn FnMut(String) + Send),
+) -> anyhow::Result<Translation> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let user_content = super::build_prompt(&req.text, req.image.is_some(), &req.target_lang);
+
+    let mut parts = Vec::new();
+    if let Some(img) = &req.image {
+        parts.push(serde_json::json!({
+            "inlineData": {
+                "mimeType": img.mime_type,
+                "data": general_purpose::STANDARD.encode(&img.bytes),
+            }
+        }));
+    }
+    parts.push(serde_json::json!({ "text": user_content }));
+
+    let body = serde_json::json!({
+        "contents": [{ "role": "user", "parts": parts }],
+        "generationConfig": { "temperature": 0.1, "maxOutputTokens": req.max_tokens },
+    });
+
+    let mut resp = crate::http::send(http.apply(request_builder)?.json(&body), http.timeouts()).await?;
+
+    if !resp.status().is_success() {
+        return Err(ApiError::from_response(resp).await.into());
+    }
+
+    let is_sse = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    let mut full_text = String::new();
+    let mut finished = false;
+    let mut truncated = false;
+    let mut handle = |parsed: serde_json::Value| -> anyhow::Result<bool> {
+        if let Some(e) = super::stream_error(&parsed) {
+            anyhow::bail!("Gemini stream error: {}", e);
+        }
+        if let Some(text) = candidate_text(&parsed) {
+            full_text.push_str(&text);
+            on_chunk(text);
+        }
+        let finish_reason = parsed["candidates"][0]["finishReason"].as_str();
+        finished = finish_reason.is_some();
+        if finished {
+            truncated = finish_reason == Some("MAX_TOKENS");
+            // Every chunk carries the counts so far; the last one has the totals
+            let usage = &parsed["usageMetadata"];
+            if usage.is_object() {
+                crate::usage::record(
+                    model,
+                    usage["promptTokenCount"].as_u64().unwrap_or(0),
+                    usage["candidatesTokenCount"].as_u64().unwrap_or(0),
+                );
+            }
+        }
+        Ok(finished)
+    };
+
+    if is_sse {
+        crate::sse::for_each_event(resp, |event| handle(super::parse_event_json(&event.data)?)).await?;
+    } else {
+        // Without `alt=sse` (some gateways strip query params) Gemini streams
+        // one big JSON array, element by element.
+        let mut splitter = JsonArraySplitter::default();
+        'outer: while let Some(chunk) = crate::http::next_chunk(&mut resp).await? {
+            for parsed in splitter.push(&chunk)? {
+                if handle(parsed)? {
+                    break 'outer;
+                }
+            }
+        }
+    }
+
+    if full_text.is_empty() {
+        return Err(EmptyResponse("Gemini").into());
+    }
+    if !finished {
+        anyhow::bail!("The stream ended before the translation was complete");
+    }
+
+    Ok(Translation { truncated, ..full_text.into() })
+}
+
+/// Concatenated text of all parts of the first candidate.
+fn candidate_text(v: &serde_json::Value) -> Option<String> {
+    let parts = v["candidates"][0]["content"]["parts"].as_array()?;
+    let text: String = parts.iter().filter_map(|p| p["text"].as_str()).collect();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// Incrementally splits a streamed JSON array (`[{...},{...}]`) into its
+/// top-level objects, regardless of where the chunk boundaries fall. An
+/// object that is not valid JSON is an error, like a malformed SSE event.
+#[derive(Default)]
+struct JsonArraySplitter {
+    buf: Vec<u8>,
+    pos: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonArraySplitter {
+    fn push(&mut self, bytes: &[u8]) -> anyhow::Result<Vec<serde_json::Value>> {
+        self.buf.extend_from_slice(bytes);
+        let mut out = Vec::new();
+        let mut start = if self.depth > 0 { Some(0) } else { None };
+
+        while self.pos < self.buf.len() {
+            let b = self.buf[self.pos];
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if b == b'\\' {
+                    self.escaped = true;
+                } else if b == b'"' {
+                    self.in_string = false;
+                }
+            } else {
+                match b {
+                    b'"' => self.in_string = true,
+                    b'{' => {
+                        if self.depth == 0 {
+                            start = Some(self.pos);
+                        }
+                        self.depth += 1;
+                    }
+                    b'}' if self.depth > 0 => {
+                        self.depth -= 1;
+                        if self.depth == 0 {
+                            if let Some(s) = start.take() {
+                                out.push(super::parse_event_json(&String::from_utf8_lossy(&self.buf[s..=self.pos]))?);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.pos += 1;
+        }
+
+        // Keep only the unfinished object (if any) for the next chunk
+        let keep_from = start.unwrap_or(self.buf.len());
+        self.buf.drain(..keep_from);
+        self.pos = self.buf.len();
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_chunks(chunks: &[&[u8]]) -> Vec<serde_json::Value> {
+        let mut splitter = JsonArraySplitter::default();
+        chunks.iter().flat_map(|c| splitter.push(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn elements_split_across_chunks() {
+        let stream = r#"[{"candidates":[{"content":{"parts":[{"text":"你好"}]}}]},
+{"candidates":[{"finishReason":"STOP"}]}]"#
+            .as_bytes();
+        let expected = split_chunks(&[stream]);
+        assert_eq!(expected.len(), 2);
+        assert_eq!(candidate_text(&expected[0]).as_deref(), Some("你好"));
+        for split in 0..=stream.len() {
+            let (a, b) = stream.split_at(split);
+            assert_eq!(split_chunks(&[a, b]), expected, "split at byte {}", split);
+        }
+        let bytewise: Vec<&[u8]> = stream.chunks(1).collect();
+        assert_eq!(split_chunks(&bytewise), expected);
+    }
+
+    #[test]
+    fn brackets_and_escapes_inside_strings() {
+        let stream = br#"[{"text":"} ] { [ \"quoted\" \\"},{"text":"\\\"}"}]"#;
+        let values = split_chunks(&[&stream[..15], &stream[15..]]);
+        assert_eq!(values, [serde_json::json!({ "text": "} ] { [ \"quoted\" \\" }), serde_json::json!({ "text": "\\\"}" })]);
+    }
+
+    #[test]
+    fn malformed_element_is_an_error() {
+        let mut splitter = JsonArraySplitter::default();
+        assert!(splitter.push(br#"[{"text": nope}"#).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! Google Translate through the free `gtx` web endpoint. No API key required.
+
+use super::{
+    ApiError, ApiKey, Capabilities, DictEntry, EmptyResponse, ModelList, ProviderInfo, Sense, Translation, TranslationProvider, TranslationRequest,
+};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use crate::logger;
+use futures_util::future::BoxFuture;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "google_free",
+    aliases: &[],
+    label: "Google (Free)",
+    short_label: "Google",
+    default_api_base: GTX_BASE,
+    default_model: "",
+    api_key: ApiKey::None,
+    api_key_hint: "",
+    capabilities: Capabilities { text: true, image: false, detect_language: false, list_languages: false, long_text: true, dictionary: true },
+    models: ModelList::None,
+    build: |cfg: &Config| {
+        Box::new(GoogleFreeProvider {
+            api_base: cfg.api_base.trim_end_matches('/').to_string(),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+/// Most characters gtx translates in one request.
+const MAX_CHARS: usize = 5000;
+
+const GTX_BASE: &str = "https://translate.googleapis.com";
+
+pub struct GoogleFreeProvider {
+    api_base: String,
+    http: ClientOptions,
+}
+
+impl GoogleFreeProvider {
+    /// Where to send requests. A base URL left over from another backend,
+    /// which used to be kept when switching to Google, means gtx itself.
+    fn base(&self) -> &str {
+        if self.api_base.is_empty() || super::is_default_api_base(&self.api_base) {
+            GTX_BASE
+        } else {
+            &self.api_base
+        }
+    }
+}
+
+impl TranslationProvider for GoogleFreeProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            let target_code = super::map_lang_to_code(&req.target_lang);
+            // An estimated token is at most four characters
+            let segments = super::segment::split(&req.text, MAX_CHARS / 4);
+            let mut out = Translation { untranslated: true, ..Translation::default() };
+            for segment in &segments {
+                let (text, detected) = translate_via_google_free(&self.http, self.base(), &segment.text, &req.target_lang).await?;
+                // A segment already in the target language is kept as it is,
+                // so a quote in a foreign text does not get reworded
+                let kept = detected.as_deref().is_some_and(|lang| super::same_language(lang, target_code));
+                if kept {
+                    logger::log(&format!("Google: segment is already in {}, not translated", target_code));
+                } else {
+                    out.untranslated = false;
+                }
+                if out.source_lang.is_none() {
+                    out.source_lang = detected;
+                }
+                // Each segment shows up as soon as it is translated
+                let chunk = format!("{}{}", if kept { &segment.text } else { &text }, segment.separator);
+                out.text.push_str(&chunk);
+                on_chunk(chunk);
+            }
+            Ok(out)
+        })
+    }
+
+    fn lookup<'a>(&'a self, word: &'a str, target_lang: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<DictEntry>>> {
+        Box::pin(async move {
+            let url = format!("{}/translate_a/single", self.base());
+            let params = [
+                ("client", "gtx"),
+                ("sl", "auto"),
+                ("tl", super::map_lang_to_code(target_lang)),
+                // Translation, dictionary, alternative translations, romanization
+                ("dt", "t"),
+                ("dt", "bd"),
+                ("dt", "at"),
+                ("dt", "rm"),
+                // Named fields instead of nested arrays
+                ("dj", "1"),
+                ("q", word),
+            ];
+            let request = self.http.apply(crate::http::client(&self.http)?.get(&url).query(&params))?;
+            let resp = crate::http::send(request, self.http.timeouts()).await?;
+            if !resp.status().is_success() {
+                return Err(ApiError::from_response(resp).await.into());
+            }
+            let json: serde_json::Value = crate::http::json(resp).await?;
+            Ok(parse_dictionary(word, &json).into_iter().collect())
+        })
+    }
+}
+
+/// Dictionary entry from a `dj=1` response: the translation with its
+/// romanization, meanings by part of speech and alternative translations.
+/// `None` when Google has nothing beyond the plain translation.
+fn parse_dictionary(word: &str, json: &serde_json::Value) -> Option<DictEntry> {
+    let sentences = json["sentences"].as_array()?;
+    let translation: String = sentences.iter().filter_map(|s| s["trans"].as_str()).collect();
+    let romanization = sentences.iter().find(|s| s.get("translit").is_some() || s.get("src_translit").is_some());
+    let translit = romanization.and_then(|s| s["translit"].as_str());
+    let src_translit = romanization.and_then(|s| s["src_translit"].as_str());
+
+    let mut senses = Vec::new();
+    for dict in json["dict"].as_array().into_iter().flatten() {
+        let meanings: Vec<String> = dict["entry"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let word = entry["word"].as_str()?;
+                let reverse: Vec<&str> = entry["reverse_translation"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_str())
+                    .collect();
+                Some(if reverse.is_empty() { word.to_string() } else { format!("{} — {}", word, reverse.join(", ")) })
+            })
+            .collect();
+        if !meanings.is_empty() {
+            senses.push(Sense { part_of_speech: dict["pos"].as_str().unwrap_or_default().to_string(), meanings });
+        }
+    }
+
+    let alternatives: Vec<String> = json["alternative_translations"][0]["alternative"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|alt| alt["word_postproc"].as_str())
+        .filter(|alt| *alt != translation)
+        .map(str::to_string)
+        .collect();
+    if senses.is_empty() && alternatives.is_empty() {
+        return None;
+    }
+    if !alternatives.is_empty() {
+        senses.push(Sense { part_of_speech: "alternatives".to_string(), meanings: alternatives });
+    }
+
+    let main = match translit {
+        Some(translit) if !translit.is_empty() => format!("{} ({})", translation, translit),
+        _ => translation.clone(),
+    };
+    senses.insert(0, Sense { part_of_speech: String::new(), meanings: vec![main] });
+
+    Some(DictEntry {
+        headword: word.to_string(),
+        pronunciation: src_translit.filter(|s| !s.is_empty()).map(str::to_string),
+        translation,
+        senses,
+        source: INFO.label.to_string(),
+        source_lang: json["src"].as_str().map(str::to_string),
+    })
+}
+
+/// Returns the translation and the detected source language code.
+async fn translate_via_google_free(
+    http: &ClientOptions,
+    base: &str,
+    input: &str,
+    target_lang: &str,
+) -> anyhow::Result<(String, Option<String>)> {
+    let target_code = super::map_lang_to_code(target_lang);
+
+    // Google Translate Free API (gtx)
+    // url = "https://translate.googleapis.com/translate_a/single?client=gtx&sl=auto&tl={}&dt=t", q in the body
+
+    let url = format!("{}/translate_a/single", base);
+    let params = [
+        ("client", "gtx"),
+        ("sl", "auto"),
+        ("tl", target_code),
+        ("dt", "t"),
+    ];
+
+    // The text goes in a form body rather than the URL, which gets too long
+    let request = http.apply(crate::http::client(http)?.post(&url).query(&params))?.form(&[("q", input)]);
+    let resp = crate::http::send(request, http.timeouts()).await?;
+
+    if !resp.status().is_success() {
+        return Err(ApiError::from_response(resp).await.into());
+    }
+
+    // The response is a nested JSON array.
+    // [[["Translation", "Original", null, null, 1]], null, "en", ...]
+    // We need to iterate over the first array and join the first element of each inner array.
+
+    let json: serde_json::Value = crate::http::json(resp).await?;
+
+    let mut full_text = String::new();
+
+    if let Some(sentences) = json.as_array().and_then(|arr| arr.first()).and_then(|v| v.as_array()) {
+        for sentence in sentences {
+            if let Some(text) = sentence.as_array().and_then(|arr| arr.first()).and_then(|v| v.as_str()) {
+                full_text.push_str(text);
+            }
+        }
+    } else {
+        anyhow::bail!("Failed to parse Google Translate response");
+    }
+
+    if full_text.is_empty() {
+        return Err(EmptyResponse("Google Translate").into());
+    }
+
+    let detected = json.get(2).and_then(|v| v.as_str()).map(str::to_string);
+
+    Ok((full_text, detected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay;
+
+    fn provider(api_base: &str) -> Box<dyn TranslationProvider> {
+        (INFO.build)(&Config { api_type: INFO.id.to_string(), api_base: api_base.to_string(), ..Config::default() })
+    }
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest { text: text.to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 }
+    }
+
+    #[tokio::test]
+    async fn text_is_posted_and_translated() {
+        let server = replay::serve("google_free", GTX_BASE).await;
+        let out = provider(&server.url).translate(&request("Hello, world!"), &mut |_| {}).await.unwrap();
+        assert_eq!(out.text, "你好，世界！");
+        assert_eq!(out.source_lang.as_deref(), Some("en"));
+        assert!(!out.untranslated);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.method, "POST");
+        assert!(sent.target.starts_with("/translate_a/single?") && !sent.target.contains("q="));
+        assert_eq!(String::from_utf8_lossy(&sent.body), "q=Hello%2C+world%21");
+    }
+
+    #[tokio::test]
+    async fn text_in_the_target_language_is_kept() {
+        let server = replay::serve("google_free_same_language", GTX_BASE).await;
+        // Each segment is checked on its own and kept as it was
+        let input = format!("{}\n\n{}", "你好，世界！".repeat(800), "再见。".repeat(800));
+        let out = provider(&server.url).translate(&request(&input), &mut |_| {}).await.unwrap();
+        assert_eq!(out.text, input);
+        assert_eq!(out.source_lang.as_deref(), Some("zh-CN"));
+        assert!(out.untranslated);
+        assert!(server.requests().len() > 1);
+    }
+
+    #[test]
+    fn base_url_left_over_from_another_backend_is_ignored() {
+        let base = |api_base: &str| GoogleFreeProvider { api_base: api_base.to_string(), http: ClientOptions::default() }.base().to_string();
+        assert_eq!(base(""), GTX_BASE);
+        assert_eq!(base("https://api.openai.com/v1"), GTX_BASE);
+        assert_eq!(base("http://127.0.0.1:8080"), "http://127.0.0.1:8080");
+    }
+
+    #[test]
+    fn dictionary_sections_become_senses() {
+        let json = serde_json::json!({
+            "sentences": [
+                { "trans": "银行", "orig": "bank" },
+                { "translit": "Yínháng", "src_translit": "baNGk" }
+            ],
+            "dict": [
+                { "pos": "noun", "terms": ["银行", "岸"], "entry": [
+                    { "word": "银行", "reverse_translation": ["bank"] },
+                    { "word": "岸", "reverse_translation": ["shore", "bank"] }
+                ]},
+                { "pos": "verb", "entry": [{ "word": "存" }] }
+            ],
+            "alternative_translations": [{ "src_phrase": "bank", "alternative": [
+                { "word_postproc": "银行" }, { "word_postproc": "岸边" }
+            ]}],
+            "src": "en"
+        });
+        let entry = parse_dictionary("bank", &json).unwrap();
+        assert_eq!(entry.pronunciation.as_deref(), Some("baNGk"));
+        assert_eq!(entry.source_lang.as_deref(), Some("en"));
+        assert_eq!(entry.translation, "银行");
+        let senses: Vec<(&str, Vec<&str>)> = entry
+            .senses
+            .iter()
+            .map(|s| (s.part_of_speech.as_str(), s.meanings.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(
+            senses,
+            vec![
+                ("", vec!["银行 (Yínháng)"]),
+                ("noun", vec!["银行 — bank", "岸 — shore, bank"]),
+                ("verb", vec!["存"]),
+                ("alternatives", vec!["岸边"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_translation_is_not_an_entry() {
+        let json = serde_json::json!({ "sentences": [{ "trans": "你好吗", "orig": "how are you" }], "src": "en" });
+        assert!(parse_dictionary("how are you", &json).is_none());
+    }
+}
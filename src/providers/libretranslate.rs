@@ -0,0 +1,247 @@
+//! LibreTranslate and other self-hosted servers speaking its HTTP API.
+
+use super::{ApiError, ApiKey, Capabilities, EmptyResponse, Language, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use futures_util::future::BoxFuture;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "libretranslate",
+    aliases: &["libre"],
+    label: "LibreTranslate (Self-hosted)",
+    short_label: "Libre",
+    default_api_base: "http://localhost:5000",
+    default_model: "",
+    api_key: ApiKey::Optional,
+    api_key_hint: "Only needed if the server requires keys",
+    capabilities: Capabilities { text: true, image: false, detect_language: true, list_languages: true, long_text: false, dictionary: false },
+    models: ModelList::None,
+    build: |cfg: &Config| {
+        Box::new(LibreTranslateProvider {
+            api_key: cfg.openai_api_key.clone(),
+            api_base: cfg.api_base.trim_end_matches('/').to_string(),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct LibreTranslateProvider {
+    api_key: String,
+    api_base: String,
+    http: ClientOptions,
+}
+
+impl LibreTranslateProvider {
+    /// Adds `api_key` to a request body when one is configured.
+    fn with_key(&self, mut body: serde_json::Value) -> serde_json::Value {
+        if !self.api_key.is_empty() {
+            body["api_key"] = self.api_key.clone().into();
+        }
+        body
+    }
+
+    async fn post(&self, path: &str, body: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        let request = self.http.apply(crate::http::client(&self.http)?.post(format!("{}{}", self.api_base, path)))?
+            .json(&self.with_key(body));
+        let resp = crate::http::send(request, self.http.timeouts()).await?;
+
+        if !resp.status().is_success() {
+            let mut err = ApiError::from_response(resp).await;
+            // LibreTranslate reports errors as {"error": "..."}
+            if let Some(msg) = serde_json::from_str::<serde_json::Value>(&err.body)
+                .ok()
+                .and_then(|v| v["error"].as_str().map(str::to_string))
+            {
+                err.body = msg;
+            }
+            return Err(err.into());
+        }
+
+        crate::http::json(resp).await
+    }
+}
+
+impl TranslationProvider for LibreTranslateProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            let json = self
+                .post("/translate", serde_json::json!({
+                    "q": req.text,
+                    "source": "auto",
+                    "target": libre_lang_code(&req.target_lang),
+                    "format": "text",
+                }))
+                .await?;
+
+            let text = json["translatedText"].as_str().unwrap_or_default().to_string();
+            if text.is_empty() {
+                return Err(EmptyResponse("LibreTranslate").into());
+            }
+            // LibreTranslate does not stream; deliver the whole result at once
+            on_chunk(text.clone());
+            let source_lang = json["detectedLanguage"]["language"].as_str().map(|code| super::map_lang_to_code(code).to_string());
+            Ok(Translation { source_lang, ..text.into() })
+        })
+    }
+
+    fn detect_language<'a>(&'a self, text: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(async move {
+            // [{"confidence": 90.0, "language": "en"}, ...], most likely first
+            let json = self.post("/detect", serde_json::json!({ "q": text })).await?;
+            let Some(code) = json[0]["language"].as_str() else {
+                anyhow::bail!("LibreTranslate /detect found no language");
+            };
+            // Its "zh" and "zt" become zh-CN and zh-TW, so they are told apart
+            Ok(super::map_lang_to_code(code).to_string())
+        })
+    }
+
+    fn languages(&self) -> BoxFuture<'_, anyhow::Result<Vec<Language>>> {
+        Box::pin(async move {
+            let mut req = crate::http::client(&self.http)?.get(format!("{}/languages", self.api_base));
+            if !self.api_key.is_empty() {
+                req = req.query(&[("api_key", self.api_key.as_str())]);
+            }
+            let resp = crate::http::send(self.http.apply(req)?, self.http.timeouts()).await?;
+            if !resp.status().is_success() {
+                anyhow::bail!("LibreTranslate /languages returned status: {}", resp.status());
+            }
+
+            // [{"code": "en", "name": "English", "targets": [...]}, ...]
+            let json: serde_json::Value = crate::http::json(resp).await?;
+            let languages = json
+                .as_array()
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|l| {
+                            Some(Language {
+                                code: l["code"].as_str()?.to_string(),
+                                name: l["name"].as_str()?.to_string(),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Ok(languages)
+        })
+    }
+}
+
+/// LibreTranslate accepts its own codes as-is (these are what the settings
+/// dropdown stores); free-form names go through the shared table.
+fn libre_lang_code(lang: &str) -> String {
+    let is_code = ((2..=3).contains(&lang.len()) && lang.chars().all(|c| c.is_ascii_lowercase()))
+        || (lang.len() <= 7 && lang.contains('-') && lang.chars().all(|c| c.is_ascii_alphabetic() || c == '-'));
+    if is_code {
+        return lang.to_string();
+    }
+    match super::map_lang_to_code(lang) {
+        "zh-CN" => "zh".to_string(),
+        "zh-TW" => "zt".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay;
+
+    const UPSTREAM: &str = "http://localhost:5000";
+
+    fn config(api_base: &str) -> Config {
+        Config {
+            api_type: INFO.id.to_string(),
+            api_base: api_base.to_string(),
+            openai_api_key: "libre-key".to_string(),
+            ..Config::default()
+        }
+    }
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest { text: text.to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 }
+    }
+
+    #[tokio::test]
+    async fn text_is_translated() {
+        let server = replay::serve("libretranslate_translate", UPSTREAM).await;
+        let mut chunks = Vec::new();
+        let out = (INFO.build)(&config(&server.url)).translate(&request("Hello, world!"), &mut |c| chunks.push(c)).await.unwrap();
+        assert_eq!(out.text, "你好，世界！");
+        assert_eq!(out.source_lang.as_deref(), Some("en"));
+        assert_eq!(chunks, [out.text]);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.target, "/translate");
+        let body = sent.json();
+        assert_eq!(body["q"], "Hello, world!");
+        assert_eq!(body["source"], "auto");
+        assert_eq!(body["target"], "zh");
+        assert_eq!(body["api_key"], "libre-key");
+    }
+
+    #[tokio::test]
+    async fn rejected_key_is_an_api_error() {
+        let server = replay::serve("libretranslate_forbidden", UPSTREAM).await;
+        let err = (INFO.build)(&config(&server.url)).translate(&request("Hello"), &mut |_| {}).await.unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!(api_error.status, 403);
+        // The message, not the JSON around it
+        assert_eq!(api_error.body, "Invalid API key");
+        assert!(!super::super::should_fall_back(&err));
+    }
+
+    #[tokio::test]
+    async fn languages_are_listed() {
+        let server = replay::serve("libretranslate_languages", UPSTREAM).await;
+        let languages = (INFO.build)(&config(&server.url)).languages().await.unwrap();
+        let codes: Vec<&str> = languages.iter().map(|l| l.code.as_str()).collect();
+        assert_eq!(codes, ["en", "zh", "zt"]);
+        assert_eq!(languages[2].name, "Chinese (traditional)");
+        assert_eq!(server.requests()[0].target, "/languages?api_key=libre-key");
+    }
+
+    #[tokio::test]
+    async fn language_is_detected() {
+        let server = replay::serve("libretranslate_detect", UPSTREAM).await;
+        let lang = (INFO.build)(&config(&server.url)).detect_language("你好，世界！").await.unwrap();
+        assert_eq!(lang, "zh-CN");
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.target, "/detect");
+        assert_eq!(sent.json()["q"], "你好，世界！");
+    }
+
+    #[tokio::test]
+    async fn text_in_the_target_language_is_kept() {
+        let server = replay::serve("libretranslate_detect", UPSTREAM).await;
+        let chain = super::super::chain_from_config(&config(&server.url));
+        let (out, _) = super::super::translate_with_fallback(&chain, &request(" 你好，世界！\n"), &mut |_| {}, &mut |_| {}, &mut |_| {})
+            .await
+            .unwrap();
+        assert_eq!(out.text, "你好，世界！");
+        assert_eq!(out.source_lang.as_deref(), Some("zh-CN"));
+        assert!(out.untranslated);
+        // Only detected, never translated
+        assert_eq!(server.requests().len(), 1);
+
+        // Traditional Chinese is still a translation away
+        assert!(!super::super::same_language("zh-CN", super::super::map_lang_to_code("zt")));
+    }
+
+    #[test]
+    fn codes_and_names() {
+        assert_eq!(libre_lang_code("zt"), "zt");
+        assert_eq!(libre_lang_code("Traditional Chinese"), "zt");
+        assert_eq!(libre_lang_code("Chinese"), "zh");
+        assert_eq!(libre_lang_code("pt-BR"), "pt-BR");
+    }
+}
@@ -0,0 +1,284 @@
+//! In-process CPU inference from a quantized GGUF model file, for machines
+//! where Ollama cannot be installed. Built with the `local-llm` feature.
+
+use super::{ApiKey, Capabilities, EmptyResponse, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::logger;
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use candle_transformers::models::{quantized_gemma3, quantized_llama, quantized_qwen2};
+use futures_util::future::BoxFuture;
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "local",
+    aliases: &["gguf"],
+    label: "Local GGUF model (CPU)",
+    short_label: "Local",
+    default_api_base: "",
+    default_model: "",
+    api_key: ApiKey::None,
+    api_key_hint: "",
+    capabilities: Capabilities { text: true, image: false, detect_language: false, list_languages: false, long_text: false, dictionary: false },
+    models: ModelList::None,
+    build: |cfg: &Config| {
+        Box::new(LocalProvider {
+            model_path: PathBuf::from(&cfg.local_model_path),
+            tokenizer_path: PathBuf::from(&cfg.local_tokenizer_path),
+            threads: cfg.local_threads,
+        })
+    },
+};
+
+/// The last model loaded; loading a GGUF file takes seconds, generating a
+/// short translation usually less.
+static LOADED: Lazy<Mutex<Option<LoadedModel>>> = Lazy::new(|| Mutex::new(None));
+
+/// Pool limited to `local_threads`. Without the setting generation uses
+/// rayon's global pool on all cores.
+static POOL: Lazy<Mutex<Option<Arc<ThreadPool>>>> = Lazy::new(|| Mutex::new(None));
+
+pub struct LocalProvider {
+    model_path: PathBuf,
+    tokenizer_path: PathBuf,
+    threads: Option<usize>,
+}
+
+impl TranslationProvider for LocalProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            if self.model_path.as_os_str().is_empty() {
+                anyhow::bail!("Set the GGUF model file in settings");
+            }
+            let pool = self.threads.filter(|&n| n > 0).map(thread_pool).transpose()?;
+
+            let (model_path, tokenizer_path) = (self.model_path.clone(), self.tokenizer_path.clone());
+            let system = super::build_system_prompt(&req.target_lang);
+            let input = req.text.clone();
+            let max_tokens = req.max_tokens as usize;
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            // Inference takes seconds of CPU; off the runtime, compare columns
+            // and other segments keep streaming meanwhile
+            let task = tokio::task::spawn_blocking(move || {
+                let mut loaded = LOADED.lock().unwrap();
+                if !loaded.as_ref().is_some_and(|m| m.model_path == model_path && m.tokenizer_path == tokenizer_path) {
+                    *loaded = None; // free the old weights first
+                    *loaded = Some(LoadedModel::load(&model_path, &tokenizer_path)?);
+                }
+                let model = loaded.as_mut().unwrap();
+
+                let prompt = model.template.prompt(&system, &input);
+                let mut send = |chunk: String| {
+                    let _ = tx.send(chunk);
+                };
+                let mut generate = || model.generate(&prompt, max_tokens, &mut send);
+                // candle's CPU kernels run on the rayon pool they are called from
+                match pool {
+                    Some(pool) => pool.install(generate),
+                    None => generate(),
+                }
+            });
+
+            while let Some(chunk) = rx.recv().await {
+                on_chunk(chunk);
+            }
+            let translation = task.await??;
+            if translation.text.trim().is_empty() {
+                return Err(EmptyResponse("the local model").into());
+            }
+            Ok(Translation { text: translation.text.trim().to_string(), ..translation })
+        })
+    }
+}
+
+/// The pool with `threads` threads, built on first use or when the setting
+/// changes.
+fn thread_pool(threads: usize) -> anyhow::Result<Arc<ThreadPool>> {
+    let mut pool = POOL.lock().unwrap();
+    if let Some(pool) = pool.as_ref().filter(|p| p.current_num_threads() == threads) {
+        return Ok(Arc::clone(pool));
+    }
+    let built = Arc::new(ThreadPoolBuilder::new().num_threads(threads).thread_name(|i| format!("local-llm-{}", i)).build()?);
+    *pool = Some(Arc::clone(&built));
+    Ok(built)
+}
+
+enum Weights {
+    Qwen2(quantized_qwen2::ModelWeights),
+    Gemma3(quantized_gemma3::ModelWeights),
+    Llama(quantized_llama::ModelWeights),
+}
+
+impl Weights {
+    fn forward(&mut self, input: &Tensor, index_pos: usize) -> candle_core::Result<Tensor> {
+        match self {
+            Self::Qwen2(m) => m.forward(input, index_pos),
+            Self::Gemma3(m) => m.forward(input, index_pos),
+            Self::Llama(m) => m.forward(input, index_pos),
+        }
+    }
+}
+
+/// Chat format the model was trained on, picked from the GGUF architecture.
+enum ChatTemplate {
+    ChatMl,
+    Gemma,
+    Llama3,
+    Llama2,
+}
+
+impl ChatTemplate {
+    fn prompt(&self, system: &str, user: &str) -> String {
+        match self {
+            Self::ChatMl => format!(
+                "<|im_start|>system\n{}<|im_end|>\n<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n",
+                system, user
+            ),
+            // Gemma has no system role; the instructions go first in the user turn
+            Self::Gemma => format!(
+                "<bos><start_of_turn>user\n{}\n\n{}<end_of_turn>\n<start_of_turn>model\n",
+                system, user
+            ),
+            Self::Llama3 => format!(
+                "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\n{}<|eot_id|>\
+                 <|start_header_id|>user<|end_header_id|>\n\n{}<|eot_id|>\
+                 <|start_header_id|>assistant<|end_header_id|>\n\n",
+                system, user
+            ),
+            Self::Llama2 => format!("<s>[INST] {}\n\n{} [/INST]", system, user),
+        }
+    }
+
+    fn end_of_turn(&self) -> &'static str {
+        match self {
+            Self::ChatMl => "<|im_end|>",
+            Self::Gemma => "<end_of_turn>",
+            Self::Llama3 => "<|eot_id|>",
+            Self::Llama2 => "</s>",
+        }
+    }
+}
+
+struct LoadedModel {
+    model_path: PathBuf,
+    tokenizer_path: PathBuf,
+    weights: Weights,
+    tokenizer: Tokenizer,
+    template: ChatTemplate,
+    eos_token: u32,
+}
+
+impl LoadedModel {
+    fn load(model_path: &Path, tokenizer_path: &Path) -> anyhow::Result<Self> {
+        let started = std::time::Instant::now();
+        // GGUF files do not carry a tokenizer.json; look next to the model by default
+        let tokenizer_file = if tokenizer_path.as_os_str().is_empty() {
+            model_path.with_file_name("tokenizer.json")
+        } else {
+            tokenizer_path.to_path_buf()
+        };
+        let tokenizer = Tokenizer::from_file(&tokenizer_file)
+            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer {}: {}", tokenizer_file.display(), e))?;
+
+        let mut file = std::fs::File::open(model_path)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", model_path.display(), e))?;
+        let content = gguf_file::Content::read(&mut file)?;
+        let architecture = content
+            .metadata
+            .get("general.architecture")
+            .and_then(|v| v.to_string().ok())
+            .cloned()
+            .unwrap_or_default();
+
+        let device = Device::Cpu;
+        let (weights, template) = match architecture.as_str() {
+            "qwen2" => (Weights::Qwen2(quantized_qwen2::ModelWeights::from_gguf(content, &mut file, &device)?), ChatTemplate::ChatMl),
+            "gemma3" => (Weights::Gemma3(quantized_gemma3::ModelWeights::from_gguf(content, &mut file, &device)?), ChatTemplate::Gemma),
+            "llama" => {
+                let template = if tokenizer.token_to_id("<|eot_id|>").is_some() { ChatTemplate::Llama3 } else { ChatTemplate::Llama2 };
+                (Weights::Llama(quantized_llama::ModelWeights::from_gguf(content, &mut file, &device)?), template)
+            }
+            other => anyhow::bail!("Unsupported model architecture '{}' (supported: qwen2, gemma3, llama)", other),
+        };
+        let eos_token = tokenizer
+            .token_to_id(template.end_of_turn())
+            .ok_or_else(|| anyhow::anyhow!("Tokenizer has no {} token", template.end_of_turn()))?;
+
+        logger::log(&format!(
+            "Local model loaded: {} ({}) in {:.1}s",
+            model_path.display(),
+            architecture,
+            started.elapsed().as_secs_f32()
+        ));
+        Ok(Self {
+            model_path: model_path.to_path_buf(),
+            tokenizer_path: tokenizer_path.to_path_buf(),
+            weights,
+            tokenizer,
+            template,
+            eos_token,
+        })
+    }
+
+    /// Greedy decoding, streaming text to `on_chunk` as soon as the decoded
+    /// tokens form complete UTF-8 characters.
+    fn generate(&mut self, prompt: &str, max_new_tokens: usize, on_chunk: &mut (dyn FnMut(String) + Send)) -> anyhow::Result<Translation> {
+        let prompt_tokens = self
+            .tokenizer
+            .encode(prompt, false)
+            .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?
+            .get_ids()
+            .to_vec();
+        let device = Device::Cpu;
+        let mut sampler = LogitsProcessor::from_sampling(0, Sampling::ArgMax);
+
+        // Index 0 resets the key/value cache left over from the last request
+        let input = Tensor::new(prompt_tokens.as_slice(), &device)?.unsqueeze(0)?;
+        let logits = self.weights.forward(&input, 0)?.squeeze(0)?;
+        let mut next = sampler.sample(&logits)?;
+
+        let mut generated = Vec::new();
+        let mut text = String::new();
+        let mut truncated = true;
+        for index in 0..max_new_tokens {
+            // Cancelling cannot drop a blocking thread midway; stop here instead
+            if crate::cancel::is_cancelled() {
+                return Err(crate::cancel::Cancelled.into());
+            }
+            if next == self.eos_token {
+                truncated = false;
+                break;
+            }
+            generated.push(next);
+
+            let decoded = self
+                .tokenizer
+                .decode(&generated, true)
+                .map_err(|e| anyhow::anyhow!("Detokenization failed: {}", e))?;
+            // A multi-byte character split across tokens decodes to U+FFFD until complete
+            if !decoded.ends_with('\u{fffd}') && decoded.len() > text.len() && decoded.is_char_boundary(text.len()) {
+                on_chunk(decoded[text.len()..].to_string());
+                text = decoded;
+            }
+
+            let input = Tensor::new(&[next], &device)?.unsqueeze(0)?;
+            let logits = self.weights.forward(&input, prompt_tokens.len() + index)?.squeeze(0)?;
+            next = sampler.sample(&logits)?;
+        }
+
+        Ok(Translation { truncated, ..text.into() })
+    }
+}
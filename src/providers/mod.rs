@@ -0,0 +1,703 @@
+//! Translation backends.
+//!
+//! Every backend implements [`TranslationProvider`] and is described by a
+//! static [`ProviderInfo`] entry in [`PROVIDERS`]. The hotkey worker and the
+//! settings UI both go through this table, so adding a backend means adding
+//! one module and one entry here instead of touching `api_type` string
+//! comparisons all over the place.
+
+use crate::config::{Config, EndpointConfig};
+use crate::{logger, ImageData};
+use futures_util::future::BoxFuture;
+use std::collections::BTreeMap;
+
+mod anthropic;
+mod azure;
+mod deepl;
+mod gemini;
+mod google_free;
+mod libretranslate;
+#[cfg(feature = "local-llm")]
+mod local;
+mod ollama;
+mod openai;
+mod ratelimit;
+mod retry;
+pub mod segment;
+pub mod stardict;
+
+/// What a backend can do with the clipboard contents.
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    pub text: bool,
+    pub image: bool,
+    /// Tells which language a text is in, see [`TranslationProvider::detect_language`]
+    pub detect_language: bool,
+    pub list_languages: bool,
+    /// Splits long texts itself, so they are passed on whole
+    pub long_text: bool,
+    /// Looks up single words as dictionary entries
+    pub dictionary: bool,
+}
+
+/// Whether a backend takes an API key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKey {
+    None,
+    Optional,
+    Required,
+}
+
+/// Where the settings UI gets the model choices for a backend.
+pub enum ModelList {
+    /// The backend has no notion of a model (e.g. Google Translate)
+    None,
+    /// Fixed set of `(model id, display label)` pairs
+    Fixed(&'static [(&'static str, &'static str)]),
+    /// Models are discovered from the local Ollama server
+    Ollama,
+}
+
+/// Static description of a backend, shared by the registry and the UI.
+pub struct ProviderInfo {
+    /// Value stored in `Config.api_type`
+    pub id: &'static str,
+    /// Other `api_type` values that select this backend
+    pub aliases: &'static [&'static str],
+    /// Name shown in the settings dropdown
+    pub label: &'static str,
+    /// Name shown in the title-bar switcher
+    pub short_label: &'static str,
+    pub default_api_base: &'static str,
+    pub default_model: &'static str,
+    pub api_key: ApiKey,
+    pub api_key_hint: &'static str,
+    pub capabilities: Capabilities,
+    pub models: ModelList,
+    build: fn(&Config) -> Box<dyn TranslationProvider>,
+}
+
+/// All selectable backends, in the order they appear in the UI.
+pub static PROVIDERS: &[&ProviderInfo] = &[
+    &openai::INFO,
+    &ollama::INFO,
+    &google_free::INFO,
+    &anthropic::INFO,
+    &gemini::INFO,
+    &deepl::INFO,
+    &libretranslate::INFO,
+    &azure::INFO,
+    #[cfg(feature = "local-llm")]
+    &local::INFO,
+];
+
+/// A target language offered by a backend.
+#[derive(Debug, Clone)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+}
+
+/// A dictionary entry for a single word, shown in a structured panel
+/// instead of plain translated text.
+#[derive(Debug, Clone)]
+pub struct DictEntry {
+    pub headword: String,
+    pub pronunciation: Option<String>,
+    /// What goes to the clipboard: the main translation, or the first
+    /// definition for monolingual dictionaries
+    pub translation: String,
+    pub senses: Vec<Sense>,
+    /// Dictionary or backend the entry came from
+    pub source: String,
+    /// Language code of the headword, when the backend detected it
+    pub source_lang: Option<String>,
+}
+
+/// Meanings of a word, grouped by part of speech (empty if unknown).
+#[derive(Debug, Clone)]
+pub struct Sense {
+    pub part_of_speech: String,
+    pub meanings: Vec<String>,
+}
+
+impl DictEntry {
+    /// Plain-text form for the clipboard.
+    pub fn to_plain_text(&self) -> String {
+        let mut out = self.headword.clone();
+        if let Some(pronunciation) = &self.pronunciation {
+            out.push_str(&format!(" [{}]", pronunciation));
+        }
+        for sense in &self.senses {
+            out.push('\n');
+            if !sense.part_of_speech.is_empty() {
+                out.push_str(&format!("{}: ", sense.part_of_speech));
+            }
+            out.push_str(&sense.meanings.join("; "));
+        }
+        out
+    }
+}
+
+/// True for clipboard text worth a backend dictionary lookup: a single
+/// word. Phrases are translated as usual.
+pub fn is_word_lookup(text: &str) -> bool {
+    is_phrase_lookup(text) && !text.trim().contains(char::is_whitespace)
+}
+
+/// True for clipboard text worth looking up in StarDict dictionaries, which
+/// also have entries for phrases such as "look up" or "in spite of": up to
+/// four words, none of them a sentence.
+pub fn is_phrase_lookup(text: &str) -> bool {
+    const SENTENCE_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '。', '，', '、', '；', '：', '！', '？', '…'];
+    let text = text.trim();
+    if text.is_empty() || text.chars().count() > 48 || text.split_whitespace().count() > 4 {
+        return false;
+    }
+    if text.contains(SENTENCE_PUNCTUATION) {
+        return false;
+    }
+    // Chinese, Japanese and Thai leave no space between words, so a whole
+    // sentence would pass as one word. Their words are a few characters long
+    let ideographs = text.chars().filter(|&c| matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F)).count();
+    let southeast_asian = text.chars().filter(|&c| matches!(c as u32, 0x0E00..=0x0EFF | 0x1000..=0x109F | 0x1780..=0x17FF)).count();
+    ideographs <= 4 && southeast_asian <= 10
+}
+
+/// A single translation job.
+pub struct TranslationRequest {
+    pub text: String,
+    pub image: Option<ImageData>,
+    pub target_lang: String,
+    /// Longest answer to ask for
+    pub max_tokens: u32,
+}
+
+/// What a backend made of a [`TranslationRequest`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Translation {
+    pub text: String,
+    /// The backend stopped at `max_tokens`, so the end is missing
+    pub truncated: bool,
+    /// Language code the backend detected in the text, if it says
+    pub source_lang: Option<String>,
+    /// The text is already in the target language and was kept as it is
+    pub untranslated: bool,
+}
+
+impl From<String> for Translation {
+    fn from(text: String) -> Self {
+        Self { text, ..Self::default() }
+    }
+}
+
+/// Whether the language code a backend detected is the target's. A code
+/// without a region matches any region ("zh" and "zh-CN"), but two regions
+/// must agree: zh-CN and zh-TW are written differently.
+pub(crate) fn same_language(detected: &str, target: &str) -> bool {
+    let normalize = |code: &str| code.trim().replace('_', "-").to_ascii_lowercase();
+    let (detected, target) = (normalize(detected), normalize(target));
+    if detected == target {
+        return true;
+    }
+    match (detected.split_once('-'), target.split_once('-')) {
+        (Some(_), Some(_)) => false,
+        (Some((primary, _)), None) => primary == target,
+        (None, Some((primary, _))) => primary == detected,
+        (None, None) => false,
+    }
+}
+
+pub trait TranslationProvider: Send + Sync {
+    fn info(&self) -> &'static ProviderInfo;
+
+    /// Translate `req`, feeding partial output to `on_chunk` as it arrives.
+    /// Returns the full translated text.
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>>;
+
+    /// Language code of `text`. Only called when
+    /// `capabilities.detect_language` is set.
+    fn detect_language<'a>(&'a self, _text: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        let label = self.info().label;
+        Box::pin(async move { anyhow::bail!("{} cannot detect languages", label) })
+    }
+
+    /// Dictionary entries for `word`, translated to `target_lang`; empty if
+    /// the backend knows none. Only called when `capabilities.dictionary`
+    /// is set.
+    fn lookup<'a>(&'a self, _word: &'a str, _target_lang: &'a str) -> BoxFuture<'a, anyhow::Result<Vec<DictEntry>>> {
+        let label = self.info().label;
+        Box::pin(async move { anyhow::bail!("{} cannot look up words", label) })
+    }
+
+    /// Target languages supported by the backend. Only called when
+    /// `capabilities.list_languages` is set.
+    fn languages(&self) -> BoxFuture<'_, anyhow::Result<Vec<Language>>> {
+        let label = self.info().label;
+        Box::pin(async move { anyhow::bail!("{} cannot list languages", label) })
+    }
+}
+
+/// Index into [`PROVIDERS`] for an `api_type` value, falling back to OpenAI.
+pub fn index_of(api_type: &str) -> usize {
+    PROVIDERS
+        .iter()
+        .position(|p| p.id == api_type || p.aliases.contains(&api_type))
+        .unwrap_or(0)
+}
+
+pub fn info(api_type: &str) -> &'static ProviderInfo {
+    PROVIDERS[index_of(api_type)]
+}
+
+/// Build the backend selected by `cfg.api_type`.
+pub fn from_config(cfg: &Config) -> Box<dyn TranslationProvider> {
+    (info(&cfg.api_type).build)(cfg)
+}
+
+/// A configured backend, ready to translate.
+pub struct Endpoint {
+    pub provider: Box<dyn TranslationProvider>,
+    /// Model in use, empty for backends without models
+    pub model: String,
+    /// The backend needs a key and none is configured; it is skipped.
+    pub missing_key: bool,
+    pub max_retries: u32,
+    rate_limit: ratelimit::RateLimit,
+}
+
+impl Endpoint {
+    fn new(cfg: &Config) -> Self {
+        let info = info(&cfg.api_type);
+        Self {
+            provider: (info.build)(cfg),
+            model: match info.models {
+                ModelList::None => String::new(),
+                _ => cfg.openai_model.clone(),
+            },
+            missing_key: info.api_key == ApiKey::Required && cfg.openai_api_key.is_empty(),
+            max_retries: cfg.max_retries,
+            rate_limit: ratelimit::RateLimit::from_config(cfg),
+        }
+    }
+
+    pub fn info(&self) -> &'static ProviderInfo {
+        self.provider.info()
+    }
+
+    /// Backend and model, e.g. "OpenAI · gpt-4o-mini".
+    pub fn label(&self) -> String {
+        if self.model.is_empty() {
+            self.info().short_label.to_string()
+        } else {
+            format!("{} · {}", self.info().short_label, self.model)
+        }
+    }
+}
+
+/// The primary backend followed by `cfg.fallback_providers`, in order.
+pub fn chain_from_config(cfg: &Config) -> Vec<Endpoint> {
+    endpoints(cfg, &cfg.fallback_providers)
+}
+
+/// The primary backend followed by `cfg.compare_providers`, one per column
+/// of the comparison view.
+pub fn compare_from_config(cfg: &Config) -> Vec<Endpoint> {
+    endpoints(cfg, &cfg.compare_providers)
+}
+
+fn endpoints(cfg: &Config, extra: &[EndpointConfig]) -> Vec<Endpoint> {
+    let primary = info(&cfg.api_type);
+    let mut endpoints = vec![Endpoint::new(cfg)];
+    for entry in extra {
+        endpoints.push(Endpoint::new(&extra_config(cfg, primary, entry)));
+    }
+    endpoints
+}
+
+/// Config for a fallback or compare entry. Empty fields inherit the primary settings
+/// when it is the same backend, and the backend defaults otherwise.
+fn extra_config(cfg: &Config, primary: &ProviderInfo, entry: &EndpointConfig) -> Config {
+    let info = info(&entry.api_type);
+    let same_backend = std::ptr::eq(info, primary);
+    let pick = |value: &str, primary_value: &str, default: &str| {
+        if !value.is_empty() {
+            value.to_string()
+        } else if same_backend {
+            primary_value.to_string()
+        } else {
+            default.to_string()
+        }
+    };
+    let pick_map = |value: &BTreeMap<String, String>, primary_value: &BTreeMap<String, String>| {
+        if value.is_empty() && same_backend { primary_value.clone() } else { value.clone() }
+    };
+    Config {
+        api_type: info.id.to_string(),
+        api_base: pick(&entry.api_base, &cfg.api_base, info.default_api_base),
+        openai_model: pick(&entry.model, &cfg.openai_model, info.default_model),
+        openai_api_key: pick(&entry.api_key, &cfg.openai_api_key, ""),
+        // Never inherited; each backend opts out of certificate checks on its own
+        allow_invalid_certs: entry.allow_invalid_certs,
+        extra_headers: pick_map(&entry.extra_headers, &cfg.extra_headers),
+        extra_query: pick_map(&entry.extra_query, &cfg.extra_query),
+        stream_usage: entry.stream_usage.or(if same_backend { cfg.stream_usage } else { None }),
+        fallback_providers: Vec::new(),
+        compare_providers: Vec::new(),
+        ..cfg.clone()
+    }
+}
+
+/// Translate with the first backend in `chain` that works. A backend is
+/// skipped if it lacks a key or cannot handle the request, and the next one
+/// is tried when it fails in a way another backend might not (see
+/// [`should_fall_back`]), after retrying temporary failures. `on_attempt` is
+/// called before each backend starts, `on_wait` while waiting for a retry.
+/// Returns the translation and the backend that produced it.
+pub async fn translate_with_fallback(
+    chain: &[Endpoint],
+    req: &TranslationRequest,
+    on_attempt: &mut (dyn FnMut(&'static ProviderInfo) + Send),
+    on_chunk: &mut (dyn FnMut(String) + Send),
+    on_wait: &mut (dyn FnMut(u64) + Send),
+) -> anyhow::Result<(Translation, &'static ProviderInfo)> {
+    let mut errors: Vec<(&'static str, anyhow::Error)> = Vec::new();
+
+    for endpoint in chain {
+        let info = endpoint.info();
+        if endpoint.missing_key {
+            errors.push((info.short_label, anyhow::anyhow!("Missing API key")));
+            continue;
+        }
+        if req.image.is_some() && !info.capabilities.image {
+            errors.push((info.short_label, anyhow::anyhow!("{} does not support image translation.", info.label)));
+            continue;
+        }
+        if req.image.is_none() && !info.capabilities.text {
+            errors.push((info.short_label, anyhow::anyhow!("{} only translates images.", info.label)));
+            continue;
+        }
+
+        on_attempt(info);
+        match translate_unless_in_target(endpoint, req, &mut *on_chunk, &mut *on_wait).await {
+            Ok(translation) => return Ok((translation, info)),
+            Err(e) if should_fall_back(&e) => {
+                logger::log(&format!("{} failed, trying next provider: {}", info.short_label, e));
+                errors.push((info.short_label, e));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // With a single backend configured the error reads exactly as before
+    if errors.len() == 1 {
+        return Err(errors.pop().unwrap().1);
+    }
+    let summary: Vec<String> = errors.iter().map(|(label, e)| format!("{}: {}", label, e)).collect();
+    anyhow::bail!("All providers failed:\n{}", summary.join("\n"))
+}
+
+/// Translate `req` with one backend, unless the backend can tell that the
+/// text is already in the target language; then it is kept as it is. A
+/// failed detection only costs the shortcut.
+async fn translate_unless_in_target(
+    endpoint: &Endpoint,
+    req: &TranslationRequest,
+    on_chunk: &mut (dyn FnMut(String) + Send),
+    on_wait: &mut (dyn FnMut(u64) + Send),
+) -> anyhow::Result<Translation> {
+    let info = endpoint.info();
+    let mut source_lang = None;
+    if req.image.is_none() && info.capabilities.detect_language {
+        match endpoint.provider.detect_language(&req.text).await {
+            Ok(lang) if same_language(&lang, map_lang_to_code(&req.target_lang)) => {
+                logger::log(&format!("{}: text is already in {}, not translated", info.short_label, lang));
+                let text = req.text.trim().to_string();
+                on_chunk(text.clone());
+                return Ok(Translation { source_lang: Some(lang), untranslated: true, ..text.into() });
+            }
+            Ok(lang) => source_lang = Some(lang),
+            Err(e) => logger::log(&format!("{}: language detection failed: {}", info.short_label, e)),
+        }
+    }
+    let translation = retry::translate_with_retry(endpoint, req, on_chunk, on_wait).await?;
+    Ok(Translation { source_lang: translation.source_lang.or(source_lang), ..translation })
+}
+
+/// Translate `req` with every endpoint at once. Partial output is fed to
+/// `on_chunk` and each result to `on_done` as soon as that endpoint
+/// finishes, along with its index. All results are returned in order.
+pub async fn translate_side_by_side(
+    endpoints: &[Endpoint],
+    req: &TranslationRequest,
+    on_chunk: &(dyn Fn(usize, String) + Sync),
+    on_done: &(dyn Fn(usize, &anyhow::Result<Translation>) + Sync),
+) -> Vec<anyhow::Result<Translation>> {
+    let jobs = endpoints.iter().enumerate().map(|(i, endpoint)| async move {
+        let info = endpoint.info();
+        let res = if endpoint.missing_key {
+            Err(anyhow::anyhow!("Missing API key"))
+        } else if req.image.is_some() && !info.capabilities.image {
+            Err(anyhow::anyhow!("{} does not support image translation.", info.label))
+        } else if req.image.is_none() && !info.capabilities.text {
+            Err(anyhow::anyhow!("{} only translates images.", info.label))
+        } else {
+            let mut on_chunk = |chunk| on_chunk(i, chunk);
+            translate_unless_in_target(endpoint, req, &mut on_chunk, &mut |_| {}).await
+        };
+        on_done(i, &res);
+        res
+    });
+    futures_util::future::join_all(jobs).await
+}
+
+/// Error status returned by a backend's HTTP API.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+    /// Wait suggested by the server before trying again
+    pub retry_after: Option<std::time::Duration>,
+}
+
+impl ApiError {
+    pub(crate) async fn from_response(resp: reqwest::Response) -> Self {
+        let status = resp.status();
+        let retry_after = retry::retry_after(resp.headers());
+        let body = crate::http::bytes(resp).await.map(|b| String::from_utf8_lossy(&b).into_owned()).unwrap_or_default();
+        Self { status, body, retry_after }
+    }
+
+    /// Overloaded, rate limited or out of quota; another backend may still work.
+    fn is_transient(&self) -> bool {
+        matches!(self.status.as_u16(), 408 | 429 | 456) || self.status.is_server_error()
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API error {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The backend answered successfully but produced no text.
+#[derive(Debug)]
+pub struct EmptyResponse(pub &'static str);
+
+impl std::fmt::Display for EmptyResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Empty response from {}", self.0)
+    }
+}
+
+impl std::error::Error for EmptyResponse {}
+
+/// Whether `err` is worth retrying with the next backend: the server could
+/// not be reached, was overloaded, or returned nothing. Bad keys, unknown
+/// models and similar configuration errors are reported right away.
+pub fn should_fall_back(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return e.is_connect() || e.is_timeout() || e.is_request() || e.is_body();
+        }
+        if let Some(e) = cause.downcast_ref::<ApiError>() {
+            return e.is_transient();
+        }
+        cause.is::<EmptyResponse>() || cause.is::<crate::http::Timeout>()
+    })
+}
+
+/// True if `api_base` is one of the auto-configured defaults, i.e. safe to
+/// replace when the user switches backends.
+pub fn is_default_api_base(api_base: &str) -> bool {
+    PROVIDERS.iter().any(|p| p.default_api_base == api_base)
+}
+
+/// True if `model` is one of the auto-configured default models.
+pub fn is_default_model(model: &str) -> bool {
+    PROVIDERS.iter().any(|p| p.default_model == model)
+}
+
+pub(crate) fn map_lang_to_code(lang: &str) -> &str {
+    match lang.to_lowercase().as_str() {
+        "chinese" | "zh" | "cn" | "zh-cn" | "simplified chinese" | "zh-hans" => "zh-CN",
+        // "zt" is LibreTranslate's
+        "traditional chinese" | "zh-tw" | "tw" | "zh-hant" | "zt" => "zh-TW",
+        "english" | "en" | "us" | "uk" => "en",
+        "american english" | "en-us" => "en-US",
+        "british english" | "en-gb" => "en-GB",
+        "japanese" | "ja" | "jp" => "ja",
+        "korean" | "ko" | "kr" => "ko",
+        "french" | "fr" => "fr",
+        "spanish" | "es" => "es",
+        "german" | "de" => "de",
+        "russian" | "ru" => "ru",
+        "italian" | "it" => "it",
+        "portuguese" | "pt" => "pt",
+        "brazilian portuguese" | "pt-br" => "pt-BR",
+        "european portuguese" | "pt-pt" => "pt-PT",
+        "dutch" | "nl" => "nl",
+        "polish" | "pl" => "pl",
+        "arabic" | "ar" => "ar",
+        "bulgarian" | "bg" => "bg",
+        "czech" | "cs" => "cs",
+        "danish" | "da" => "da",
+        "greek" | "el" => "el",
+        "estonian" | "et" => "et",
+        "finnish" | "fi" => "fi",
+        "hungarian" | "hu" => "hu",
+        "indonesian" | "id" => "id",
+        "lithuanian" | "lt" => "lt",
+        "latvian" | "lv" => "lv",
+        "norwegian" | "no" | "nb" => "no",
+        "romanian" | "ro" => "ro",
+        "slovak" | "sk" => "sk",
+        "slovenian" | "sl" => "sl",
+        "swedish" | "sv" => "sv",
+        "turkish" | "tr" => "tr",
+        "ukrainian" | "ua" => "uk",
+        "vietnamese" | "vi" => "vi",
+        "thai" | "th" => "th",
+        // Codes not listed here are passed on for the backend to judge
+        _ => lang,
+    }
+}
+
+/// Prompt shared by the LLM backends.
+/// Optimized for small models such as gemma3:270m.
+pub(crate) fn build_prompt(input: &str, has_image: bool, target_lang: &str) -> String {
+    if target_lang.to_lowercase().contains("chinese") {
+        if has_image {
+            "Translate the text in this image to Chinese, only output the translation, no other text".to_string()
+        } else {
+            format!("Translate '{}' to Chinese, only output the translation, no other text", input)
+        }
+    } else if target_lang.to_lowercase().contains("english") {
+        if has_image {
+            "Translate the text in this image to English, only output the translation, no other text".to_string()
+        } else {
+            format!("Translate '{}' to English, only output the translation, no other text", input)
+        }
+    } else if has_image {
+        format!("Translate the text in this image to {}, only output the translation, no other text", target_lang)
+    } else {
+        format!("Translate '{}' to {}, only output the translation, no other text", input, target_lang)
+    }
+}
+
+/// System prompt for chat-style backends that take the text to translate as
+/// a separate user message.
+pub(crate) fn build_system_prompt(target_lang: &str) -> String {
+    format!(
+        "You are a translation engine. Translate the user's message into {}. \
+         Only output the translation, no explanations, quotes or other text.",
+        target_lang
+    )
+}
+
+/// Message of an `{"error": ...}` object a backend sent in the middle of a
+/// stream, if `v` is one.
+pub(crate) fn stream_error(v: &serde_json::Value) -> Option<String> {
+    let error = &v["error"];
+    if error.is_null() {
+        return None;
+    }
+    Some(
+        error["message"]
+            .as_str()
+            .or(error.as_str())
+            .map_or_else(|| error.to_string(), str::to_string),
+    )
+}
+
+/// Message of an `event: error` stream event, whose data may be an
+/// `{"error": ...}` object or plain text.
+pub(crate) fn error_event_message(data: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(data)
+        .ok()
+        .and_then(|v| stream_error(&v))
+        .unwrap_or_else(|| data.trim().to_string())
+}
+
+/// Parse the JSON payload of a stream event. Anything else is an error
+/// rather than a silently dropped piece of the translation.
+pub(crate) fn parse_event_json(data: &str) -> anyhow::Result<serde_json::Value> {
+    serde_json::from_str(data).map_err(|e| anyhow::anyhow!("Malformed stream data ({}): {}", e, data))
+}
+
+/// Feed each complete line of a streamed response body to `on_line`, which
+/// returns `Ok(true)` once the backend signalled the end of the stream.
+/// Errors from `on_line` abort the stream and are returned as-is.
+pub(crate) async fn for_each_line<F>(mut resp: reqwest::Response, mut on_line: F) -> anyhow::Result<()>
+where
+    F: FnMut(&str) -> anyhow::Result<bool>,
+{
+    // Keep raw bytes so we never corrupt multi-byte UTF-8 characters (e.g.
+    // Chinese) that happen to be split across two HTTP chunks. Only convert
+    // to String once we have a complete UTF-8 sequence up to the last '\n'.
+    let mut raw_buffer: Vec<u8> = Vec::new();
+    let mut done = false;
+
+    while !done {
+        match crate::http::next_chunk(&mut resp).await? {
+            Some(chunk) => raw_buffer.extend_from_slice(&chunk),
+            None => {
+                // Stream ended: flush whatever is still in the buffer (handles
+                // servers that omit the trailing '\n' on the last chunk).
+                done = true;
+                if !raw_buffer.is_empty() {
+                    raw_buffer.push(b'\n');
+                }
+            }
+        }
+
+        // Only process complete lines (up to the last '\n').  Anything after
+        // the last '\n' is a partial line — leave it in the buffer so the next
+        // chunk can complete it.  This is the core fix for missing words when
+        // an HTTP chunk boundary falls in the middle of a "data: {...}" line.
+        let last_newline = raw_buffer.iter().rposition(|&b| b == b'\n');
+        let process_up_to = last_newline.map(|p| p + 1).unwrap_or(0);
+        if process_up_to == 0 {
+            continue; // No complete line yet
+        }
+
+        // Safe UTF-8 conversion: everything up to the split point is a
+        // sequence of complete lines, so it must be valid UTF-8.
+        let to_process = String::from_utf8_lossy(&raw_buffer[..process_up_to]).into_owned();
+        raw_buffer = raw_buffer[process_up_to..].to_vec();
+
+        for line in to_process.lines() {
+            if on_line(line)? {
+                done = true;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_and_phrases_to_look_up() {
+        for word in ["bank", "well-known", "don't", "银行", "ねこ", "สวัสดี"] {
+            assert!(is_word_lookup(word), "{}", word);
+        }
+        assert!(is_phrase_lookup("in spite of"));
+        assert!(!is_word_lookup("in spite of"));
+        // Sentences, with or without spaces
+        for text in ["Hello, world", "Stop.", "我今天很高兴见到你", "你好，世界", "今日は良い天気ですね", "วันนี้อากาศดีมากเลยครับ", "one two three four five"] {
+            assert!(!is_phrase_lookup(text), "{}", text);
+            assert!(!is_word_lookup(text), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Local models through Ollama's native API.
+
+use super::{ApiError, ApiKey, Capabilities, EmptyResponse, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use crate::logger;
+use futures_util::future::BoxFuture;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "ollama",
+    aliases: &[],
+    label: "Ollama (Free)",
+    short_label: "Ollama",
+    default_api_base: "http://localhost:11434",
+    default_model: "gemma3:1b",
+    api_key: ApiKey::None,
+    api_key_hint: "",
+    capabilities: Capabilities { text: true, image: true, detect_language: false, list_languages: false, long_text: false, dictionary: false },
+    models: ModelList::Ollama,
+    build: |cfg: &Config| {
+        Box::new(OllamaProvider {
+            model: cfg.openai_model.clone(),
+            api_base: cfg.api_base.clone(),
+            num_ctx: cfg.ollama_num_ctx,
+            temperature: cfg.ollama_temperature,
+            keep_alive: cfg.ollama_keep_alive.clone(),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct OllamaProvider {
+    model: String,
+    api_base: String,
+    num_ctx: Option<u32>,
+    temperature: Option<f32>,
+    keep_alive: Option<String>,
+    http: ClientOptions,
+}
+
+impl TranslationProvider for OllamaProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(self.translate_via_ollama_chat(req, on_chunk))
+    }
+}
+
+impl OllamaProvider {
+    /// Request body for `/api/chat`. Small models such as gemma3:270m follow
+    /// a system prompt far better than instructions inlined into the text.
+    fn chat_request(&self, req: &TranslationRequest) -> serde_json::Value {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let user = match &req.image {
+            Some(img) => serde_json::json!({
+                "role": "user",
+                "content": "Translate the text in this image.",
+                "images": [general_purpose::STANDARD.encode(&img.bytes)],
+            }),
+            None => serde_json::json!({ "role": "user", "content": req.text }),
+        };
+
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": super::build_system_prompt(&req.target_lang) },
+                user,
+            ],
+            "stream": true,
+        });
+
+        // Ollama's name for max_tokens
+        let mut options = serde_json::Map::new();
+        options.insert("num_predict".into(), req.max_tokens.into());
+        if let Some(num_ctx) = self.num_ctx {
+            options.insert("num_ctx".into(), num_ctx.into());
+        }
+        if let Some(temperature) = self.temperature {
+            options.insert("temperature".into(), temperature.into());
+        }
+        body["options"] = options.into();
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = keep_alive_value(keep_alive);
+        }
+        body
+    }
+
+    async fn translate_via_ollama_chat(
+        &self,
+        req: &TranslationRequest,
+        on_chunk: &mut (dyn FnMut(String) + Send),
+    ) -> anyhow::Result<Translation> {
+        // Use native Ollama API format
+        let endpoint = format!("{}/api/chat", self.api_base);
+        let body = self.chat_request(req);
+
+        logger::log(&format!("Ollama native API request to: {}", endpoint));
+        logger::log(&format!("Ollama model: {}", self.model));
+        logger::log(&format!("Ollama messages: {}", body["messages"].to_string().chars().take(500).collect::<String>()));
+
+        let request = self.http.apply(crate::http::client(&self.http)?.post(&endpoint))?.json(&body);
+        let resp = crate::http::send(request, self.http.timeouts()).await?;
+
+        if !resp.status().is_success() {
+            let err = ApiError::from_response(resp).await;
+
+            // Handle common Ollama error for non-vision models
+            if err.status == 500 && err.body.contains("missing data required for image input") {
+                anyhow::bail!("Ollama error: The model '{}' does not support images. Please use a vision model like 'llava'.", self.model);
+            }
+
+            return Err(err.into());
+        }
+
+        let mut full_text = String::new();
+        let mut finished = false;
+        let mut truncated = false;
+        super::for_each_line(resp, |line| {
+            // Native Ollama API: each line is a standalone JSON object.
+            let line = line.trim();
+            if line.is_empty() {
+                return Ok(false);
+            }
+            let parsed = super::parse_event_json(line)?;
+            if let Some(e) = super::stream_error(&parsed) {
+                anyhow::bail!("Ollama error: {}", e);
+            }
+            if let Some(content) = parsed["message"]["content"].as_str() {
+                if !content.is_empty() {
+                    full_text.push_str(content);
+                    on_chunk(content.to_string());
+                }
+            }
+            finished = parsed["done"].as_bool() == Some(true);
+            if finished {
+                truncated = parsed["done_reason"] == "length";
+                // The final line carries the token counts
+                crate::usage::record(
+                    &self.model,
+                    parsed["prompt_eval_count"].as_u64().unwrap_or(0),
+                    parsed["eval_count"].as_u64().unwrap_or(0),
+                );
+            }
+            Ok(finished)
+        })
+        .await?;
+
+        if full_text.is_empty() {
+            return Err(EmptyResponse("Ollama").into());
+        }
+        if !finished {
+            anyhow::bail!("The stream ended before the translation was complete");
+        }
+
+        Ok(Translation { truncated, ..full_text.into() })
+    }
+}
+
+/// Ollama accepts `keep_alive` as a duration string ("5m") or a number of
+/// seconds (0 unloads right away, -1 keeps the model loaded forever).
+fn keep_alive_value(keep_alive: &str) -> serde_json::Value {
+    match keep_alive.trim().parse::<i64>() {
+        Ok(secs) => secs.into(),
+        Err(_) => keep_alive.trim().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay;
+
+    #[tokio::test]
+    async fn lines_split_across_chunks() {
+        let server = replay::serve("ollama_chat", "http://localhost:11434").await;
+        let provider = (INFO.build)(&Config {
+            api_type: INFO.id.to_string(),
+            api_base: server.url.clone(),
+            openai_model: "gemma3:1b".to_string(),
+            ..Config::default()
+        });
+        let req = TranslationRequest { text: "Hello, world!".to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 };
+        let mut chunks = Vec::new();
+        let text = provider.translate(&req, &mut |c| chunks.push(c)).await.unwrap().text;
+        assert_eq!(text, "你好，世界！");
+        assert_eq!(chunks, ["你好", "，", "世界", "！"]);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.target, "/api/chat");
+        let body = sent.json();
+        assert_eq!(body["model"], "gemma3:1b");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "Hello, world!");
+        assert_eq!(body["options"]["num_predict"], 1024);
+    }
+
+    #[tokio::test]
+    async fn stream_cut_before_done_is_an_error() {
+        let server = replay::serve("ollama_chat_truncated", "http://localhost:11434").await;
+        let provider = (INFO.build)(&Config {
+            api_type: INFO.id.to_string(),
+            api_base: server.url.clone(),
+            openai_model: "gemma3:1b".to_string(),
+            ..Config::default()
+        });
+        let req = TranslationRequest { text: "Hello, world!".to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 };
+        let err = provider.translate(&req, &mut |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("Malformed stream data"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn stream_closed_before_done_is_an_error() {
+        let server = replay::serve("ollama_chat_unfinished", "http://localhost:11434").await;
+        let provider = (INFO.build)(&Config {
+            api_type: INFO.id.to_string(),
+            api_base: server.url.clone(),
+            openai_model: "gemma3:1b".to_string(),
+            ..Config::default()
+        });
+        let req = TranslationRequest { text: "Hello, world!".to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 };
+        let mut chunks = Vec::new();
+        let err = provider.translate(&req, &mut |c| chunks.push(c)).await.unwrap_err();
+        assert!(err.to_string().contains("ended before the translation was complete"), "{}", err);
+        // What arrived was still shown
+        assert_eq!(chunks.concat(), "你好，");
+    }
+}
@@ -0,0 +1,324 @@
+//! OpenAI and OpenAI-compatible chat completion APIs (LM Studio, OpenRouter, LocalAI, ...).
+
+use super::{ApiError, ApiKey, Capabilities, EmptyResponse, ModelList, ProviderInfo, Translation, TranslationProvider, TranslationRequest};
+use crate::config::Config;
+use crate::http::ClientOptions;
+use futures_util::future::BoxFuture;
+
+pub(super) static INFO: ProviderInfo = ProviderInfo {
+    id: "openai",
+    aliases: &["openai-compatible"],
+    label: "OpenAI",
+    short_label: "OpenAI",
+    default_api_base: "https://api.openai.com/v1",
+    default_model: "gpt-4o-mini",
+    api_key: ApiKey::Required,
+    api_key_hint: "sk-...",
+    capabilities: Capabilities { text: true, image: true, detect_language: false, list_languages: false, long_text: false, dictionary: false },
+    models: ModelList::Fixed(&[("gpt-4o-mini", "GPT-4o Mini")]),
+    build: |cfg: &Config| {
+        Box::new(OpenAiProvider {
+            api_key: cfg.openai_api_key.clone(),
+            model: cfg.openai_model.clone(),
+            api_base: cfg.api_base.clone(),
+            include_usage: cfg.stream_usage.unwrap_or_else(|| is_openai_host(&cfg.api_base)),
+            http: ClientOptions::from_config(cfg),
+        })
+    },
+};
+
+pub struct OpenAiProvider {
+    api_key: String,
+    model: String,
+    api_base: String,
+    /// Ask for token usage; `stream_usage` in the config, else [`is_openai_host`]
+    include_usage: bool,
+    http: ClientOptions,
+}
+
+/// Only OpenAI itself is known to take `stream_options`. Stricter
+/// compatible servers answer 400 to fields they do not know, so others
+/// opt in with `stream_usage`.
+fn is_openai_host(api_base: &str) -> bool {
+    reqwest::Url::parse(api_base).is_ok_and(|url| url.host_str() == Some("api.openai.com"))
+}
+
+impl TranslationProvider for OpenAiProvider {
+    fn info(&self) -> &'static ProviderInfo {
+        &INFO
+    }
+
+    fn translate<'a>(
+        &'a self,
+        req: &'a TranslationRequest,
+        on_chunk: &'a mut (dyn FnMut(String) + Send),
+    ) -> BoxFuture<'a, anyhow::Result<Translation>> {
+        Box::pin(async move {
+            let mut request_builder = crate::http::client(&self.http)?.post(format!("{}/chat/completions", self.api_base));
+            if !self.api_key.is_empty() {
+                request_builder = request_builder.bearer_auth(&self.api_key);
+            }
+            translate_via_openai_stream(
+                req,
+                &self.model,
+                request_builder,
+                &self.http,
+                self.include_usage,
+                on_chunk,
+            )
+            .await
+        })
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    temperature: f32,
+    max_tokens: Option<u32>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(serde::Serialize)]
+struct StreamOptions {
+    /// Ask for a last chunk with the token counts
+    include_usage: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum MessageContent<'a> {
+    Text(&'a str),
+    List(Vec<ContentPart<'a>>),
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+enum ContentPart<'a> {
+    Text { text: &'a str },
+    ImageUrl { image_url: ImageUrl<'a> },
+}
+
+#[derive(serde::Serialize)]
+struct ImageUrl<'a> {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<&'a str>,
+}
+
+#[derive(serde::Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: MessageContent<'a>,
+}
+
+/// Stream a chat completion. `request_builder` already points at the
+/// endpoint and carries authentication, which is where OpenAI-compatible
+/// servers and Azure deployments differ. With `include_usage` the last chunk
+/// carries the token counts.
+pub(super) async fn translate_via_openai_stream(
+    req: &TranslationRequest,
+    model: &str,
+    request_builder: reqwest::RequestBuilder,
+    http: &ClientOptions,
+    include_usage: bool,
+    on_chunk: &mut (dyn FnMut(String) + Send),
+) -> anyhow::Result<Translation> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let user_content = super::build_prompt(&req.text, req.image.is_some(), &req.target_lang);
+
+    let mut messages = Vec::new();
+    if let Some(img) = &req.image {
+        let b64 = general_purpose::STANDARD.encode(&img.bytes);
+        let data_url = format!("data:{};base64,{}", img.mime_type, b64);
+        messages.push(ChatMessage {
+            role: "user",
+            content: MessageContent::List(vec![
+                ContentPart::Text { text: &user_content },
+                ContentPart::ImageUrl {
+                    image_url: ImageUrl {
+                        url: data_url,
+                        detail: Some("high"),
+                    },
+                },
+            ]),
+        });
+    } else {
+        messages.push(ChatMessage {
+            role: "user",
+            content: MessageContent::Text(&user_content),
+        });
+    }
+
+    let req = ChatRequest {
+        model,
+        messages,
+        temperature: 0.1,
+        max_tokens: Some(req.max_tokens),
+        stream: true,
+        stream_options: include_usage.then_some(StreamOptions { include_usage: true }),
+    };
+
+    let resp = crate::http::send(http.apply(request_builder)?.json(&req), http.timeouts()).await?;
+
+    if !resp.status().is_success() {
+        return Err(ApiError::from_response(resp).await.into());
+    }
+
+    let mut full_text = String::new();
+    let mut finished = false;
+    let mut truncated = false;
+    crate::sse::for_each_event(resp, |event| {
+        if event.event == "error" {
+            anyhow::bail!("Stream error: {}", super::error_event_message(&event.data));
+        }
+        // OpenAI-compatible SSE format.
+        match event.data.trim() {
+            "[DONE]" => {
+                finished = true;
+                return Ok(true);
+            }
+            "" => return Ok(false), // keep-alive
+            _ => {}
+        }
+
+        let parsed = super::parse_event_json(&event.data)?;
+        if let Some(e) = super::stream_error(&parsed) {
+            anyhow::bail!("Stream error: {}", e);
+        }
+        if let Some(content) = parsed["choices"][0]["delta"]["content"].as_str() {
+            full_text.push_str(content);
+            on_chunk(content.to_string());
+        }
+        if let Some(reason) = parsed["choices"][0]["finish_reason"].as_str() {
+            finished = true;
+            truncated = reason == "length";
+        }
+        // Comes in its own chunk after the last choice, with `choices` empty;
+        // some compatible servers send it unasked
+        if let Some(usage) = parsed["usage"].as_object() {
+            crate::usage::record(
+                model,
+                usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+                usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0),
+            );
+        }
+        Ok(false)
+    })
+    .await?;
+
+    if full_text.is_empty() {
+        return Err(EmptyResponse("OpenAI").into());
+    }
+    if !finished {
+        anyhow::bail!("The stream ended before the translation was complete");
+    }
+
+    Ok(Translation { truncated, ..full_text.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay;
+
+    fn config(api_base: &str) -> Config {
+        Config {
+            api_type: INFO.id.to_string(),
+            api_base: api_base.to_string(),
+            openai_api_key: std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "sk-test".to_string()),
+            openai_model: "gpt-4o-mini".to_string(),
+            ..Config::default()
+        }
+    }
+
+    /// The provider as configured for api.openai.com, sending to `api_base`.
+    fn provider(api_base: &str) -> OpenAiProvider {
+        let cfg = config(api_base);
+        OpenAiProvider {
+            api_key: cfg.openai_api_key.clone(),
+            model: cfg.openai_model.clone(),
+            api_base: cfg.api_base.clone(),
+            include_usage: true,
+            http: ClientOptions::from_config(&cfg),
+        }
+    }
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest { text: text.to_string(), image: None, target_lang: "Chinese".to_string(), max_tokens: 1024 }
+    }
+
+    #[tokio::test]
+    async fn stream_split_inside_characters() {
+        let server = replay::serve("openai_stream", "https://api.openai.com/v1").await;
+        let mut chunks = Vec::new();
+        let text = provider(&server.url).translate(&request("Hello, world!"), &mut |c| chunks.push(c)).await.unwrap().text;
+        assert_eq!(text, "你好，世界！");
+        assert_eq!(chunks.concat(), text);
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.target, "/chat/completions");
+        assert!(sent.header("authorization").is_some_and(|v| v.starts_with("Bearer ")));
+        let body = sent.json();
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["stream_options"]["include_usage"], true);
+        assert_eq!(body["max_tokens"], 1024);
+    }
+
+    #[tokio::test]
+    async fn stream_closed_before_finish_is_an_error() {
+        let server = replay::serve("openai_stream_unfinished", "https://api.openai.com/v1").await;
+        let mut chunks = Vec::new();
+        let err = provider(&server.url).translate(&request("Hello, world!"), &mut |c| chunks.push(c)).await.unwrap_err();
+        assert!(err.to_string().contains("ended before the translation was complete"), "{}", err);
+        assert_eq!(chunks.concat(), "你好，世界！");
+    }
+
+    #[tokio::test]
+    async fn stopping_at_max_tokens_is_reported() {
+        let server = replay::serve("openai_stream_length", "https://api.openai.com/v1").await;
+        let out = provider(&server.url).translate(&request("Hello, world!"), &mut |_| {}).await.unwrap();
+        assert_eq!(out, Translation { truncated: true, ..String::from("你好，").into() });
+    }
+
+    #[tokio::test]
+    async fn error_event_with_plain_text_data() {
+        let server = replay::serve("openai_stream_error_event", "https://api.openai.com/v1").await;
+        let err = provider(&server.url).translate(&request("Hello, world!"), &mut |_| {}).await.unwrap_err();
+        assert_eq!(err.to_string(), "Stream error: upstream model overloaded, try again later");
+    }
+
+    #[tokio::test]
+    async fn compatible_servers_are_not_asked_for_usage() {
+        assert!(is_openai_host("https://api.openai.com/v1"));
+        assert!(!is_openai_host("http://localhost:1234/v1"));
+
+        let server = replay::serve("openai_stream", "https://api.openai.com/v1").await;
+        (INFO.build)(&config(&server.url)).translate(&request("Hello, world!"), &mut |_| {}).await.unwrap();
+        assert!(server.requests()[0].json().get("stream_options").is_none());
+    }
+
+    #[tokio::test]
+    async fn compatible_servers_can_opt_in_to_usage() {
+        let server = replay::serve("openai_stream", "https://api.openai.com/v1").await;
+        let cfg = Config { stream_usage: Some(true), ..config(&server.url) };
+        (INFO.build)(&cfg).translate(&request("Hello, world!"), &mut |_| {}).await.unwrap();
+        assert_eq!(server.requests()[0].json()["stream_options"]["include_usage"], true);
+    }
+
+    #[tokio::test]
+    async fn rejected_key_is_an_api_error() {
+        let server = replay::serve("openai_unauthorized", "https://api.openai.com/v1").await;
+        let err = provider(&server.url).translate(&request("Hello"), &mut |_| {}).await.unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!(api_error.status, 401);
+        assert!(api_error.body.contains("invalid_api_key"));
+        // Another backend would not fix a bad key
+        assert!(!super::super::should_fall_back(&err));
+    }
+}
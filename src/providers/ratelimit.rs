@@ -0,0 +1,187 @@
+//! Client-side request and token budgets per backend. Bursts of segments or
+//! compared backends wait here instead of running into 429s on a shared key.
+
+use crate::config::Config;
+use crate::{logger, ui};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Limits for one backend, from `config.rate_limits`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+    /// Requests in flight at the same time
+    pub max_concurrent: Option<u32>,
+}
+
+impl RateLimit {
+    pub fn from_config(cfg: &Config) -> Self {
+        let Some(custom) = cfg.rate_limits.get(super::info(&cfg.api_type).id) else { return Self::default() };
+        // 0 reads as "no limit" rather than "never"
+        let limit = |value: Option<u32>| value.filter(|&n| n > 0);
+        Self {
+            requests_per_minute: limit(custom.requests_per_minute),
+            tokens_per_minute: limit(custom.tokens_per_minute),
+            max_concurrent: limit(custom.max_concurrent),
+        }
+    }
+}
+
+/// Requests sent in the last minute and the requests in flight, shared by
+/// every endpoint of the same backend with the same limits.
+struct Limiter {
+    /// When each request was sent, and its estimated tokens
+    sent: Mutex<VecDeque<(Instant, u64)>>,
+    in_flight: Option<Arc<Semaphore>>,
+}
+
+/// Backend id and its limits
+type Key = (&'static str, RateLimit);
+
+static LIMITERS: Lazy<Mutex<HashMap<Key, Arc<Limiter>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Room for one request, held until it is done.
+pub struct Permit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+}
+
+/// Wait until backend `id` may send a request of about `tokens` tokens.
+/// The wait shows in the title bar, with a countdown for the per-minute
+/// budgets.
+pub async fn acquire(id: &'static str, limit: RateLimit, tokens: u64) -> Permit {
+    if limit == RateLimit::default() {
+        return Permit { _in_flight: None };
+    }
+    let limiter = LIMITERS
+        .lock()
+        .unwrap()
+        .entry((id, limit))
+        .or_insert_with(|| {
+            Arc::new(Limiter {
+                sent: Mutex::new(VecDeque::new()),
+                in_flight: limit.max_concurrent.map(|n| Arc::new(Semaphore::new(n as usize))),
+            })
+        })
+        .clone();
+
+    let in_flight = match &limiter.in_flight {
+        Some(semaphore) => Some(match Arc::clone(semaphore).try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                // No countdown: the wait lasts until a request in flight is done
+                let max = limit.max_concurrent.unwrap_or_default();
+                logger::log(&format!("{}: {} requests in flight, waiting", id, max));
+                ui::set_status(Some(format!("Waiting for rate limit ({} requests in flight)", max)));
+                let permit = Arc::clone(semaphore).acquire_owned().await.expect("semaphore is never closed");
+                ui::set_status(None);
+                permit
+            }
+        }),
+        None => None,
+    };
+
+    let mut waited = false;
+    loop {
+        let wait = reserve(&mut limiter.sent.lock().unwrap(), limit, tokens, Instant::now());
+        let Some(wait) = wait else { break };
+        if !waited {
+            logger::log(&format!("{}: rate limit reached, waiting {:.1}s", id, wait.as_secs_f32()));
+            waited = true;
+        }
+        ui::set_status(Some(format!("Waiting for rate limit ({}s)", wait.as_secs_f32().ceil() as u64)));
+        tokio::time::sleep(wait.min(Duration::from_secs(1))).await;
+    }
+    if waited {
+        ui::set_status(None);
+    }
+    Permit { _in_flight: in_flight }
+}
+
+/// Record a request at `now` if it fits the last minute's budget, otherwise
+/// return how long until enough of it frees up. A request larger than the
+/// whole token budget goes through once nothing else is in the window.
+fn reserve(sent: &mut VecDeque<(Instant, u64)>, limit: RateLimit, tokens: u64, now: Instant) -> Option<Duration> {
+    while sent.front().is_some_and(|&(at, _)| now.duration_since(at) >= MINUTE) {
+        sent.pop_front();
+    }
+    let frees_at = |(at, _): &(Instant, u64)| (*at + MINUTE).saturating_duration_since(now);
+
+    if let Some(rpm) = limit.requests_per_minute {
+        if sent.len() >= rpm as usize {
+            return Some(frees_at(&sent[sent.len() - rpm as usize]));
+        }
+    }
+    if let Some(tpm) = limit.tokens_per_minute {
+        let mut used: u64 = sent.iter().map(|&(_, t)| t).sum();
+        if used > 0 && used + tokens > tpm as u64 {
+            for entry in sent.iter() {
+                used -= entry.1;
+                if used == 0 || used + tokens <= tpm as u64 {
+                    return Some(frees_at(entry));
+                }
+            }
+        }
+    }
+    sent.push_back((now, tokens));
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(rpm: Option<u32>, tpm: Option<u32>) -> RateLimit {
+        RateLimit { requests_per_minute: rpm, tokens_per_minute: tpm, max_concurrent: None }
+    }
+
+    #[test]
+    fn requests_per_minute() {
+        let start = Instant::now();
+        let mut sent = VecDeque::new();
+        let rpm = limit(Some(2), None);
+        assert_eq!(reserve(&mut sent, rpm, 0, start), None);
+        assert_eq!(reserve(&mut sent, rpm, 0, start + Duration::from_secs(10)), None);
+        // The third has to wait for the first to leave the window
+        assert_eq!(reserve(&mut sent, rpm, 0, start + Duration::from_secs(20)), Some(Duration::from_secs(40)));
+        assert_eq!(reserve(&mut sent, rpm, 0, start + MINUTE), None);
+        assert_eq!(sent.len(), 2);
+    }
+
+    #[test]
+    fn tokens_per_minute() {
+        let start = Instant::now();
+        let mut sent = VecDeque::new();
+        let tpm = limit(None, Some(1000));
+        assert_eq!(reserve(&mut sent, tpm, 400, start), None);
+        assert_eq!(reserve(&mut sent, tpm, 400, start + Duration::from_secs(30)), None);
+        // 800 used; 600 more fits once the first 400 expire
+        assert_eq!(reserve(&mut sent, tpm, 600, start + Duration::from_secs(45)), Some(Duration::from_secs(15)));
+        assert_eq!(reserve(&mut sent, tpm, 200, start + Duration::from_secs(45)), None);
+    }
+
+    #[test]
+    fn oversized_request_waits_for_an_empty_window() {
+        let start = Instant::now();
+        let mut sent = VecDeque::new();
+        let tpm = limit(None, Some(1000));
+        assert_eq!(reserve(&mut sent, tpm, 100, start), None);
+        assert_eq!(reserve(&mut sent, tpm, 5000, start), Some(MINUTE));
+        assert_eq!(reserve(&mut sent, tpm, 5000, start + MINUTE), None);
+    }
+
+    #[tokio::test]
+    async fn requests_over_max_concurrent_wait_for_a_permit() {
+        let one = RateLimit { max_concurrent: Some(1), ..limit(None, None) };
+        let first = acquire("test", one, 0).await;
+        let mut second = Box::pin(acquire("test", one, 0));
+        assert!(futures_util::poll!(second.as_mut()).is_pending());
+        drop(first);
+        second.await;
+    }
+}
@@ -0,0 +1,213 @@
+//! Retrying a backend that is rate limited or briefly unavailable.
+
+use super::{ApiError, Endpoint, Translation, TranslationRequest};
+use crate::logger;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest wait before giving up on a backend (and falling back to the next).
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Translate with `endpoint`, retrying up to its `max_retries` times while the
+/// failure is temporary and nothing has been streamed yet. Every attempt
+/// waits for the endpoint's rate limit first and holds its slot only until
+/// it fails. `on_wait` gets the seconds left
+/// before the next attempt, once per second, then 0.
+pub(crate) async fn translate_with_retry(
+    endpoint: &Endpoint,
+    req: &TranslationRequest,
+    on_chunk: &mut (dyn FnMut(String) + Send),
+    on_wait: &mut (dyn FnMut(u64) + Send),
+) -> anyhow::Result<Translation> {
+    let provider = endpoint.provider.as_ref();
+    let max_retries = endpoint.max_retries;
+    // What token budgets count: the prompt plus the longest answer allowed
+    let tokens = super::segment::estimate_tokens(&req.text) as u64 + u64::from(req.max_tokens);
+    let mut attempt = 0;
+    loop {
+        let permit = super::ratelimit::acquire(provider.info().id, endpoint.rate_limit, tokens).await;
+        let mut streamed = false;
+        let result = provider
+            .translate(req, &mut |chunk| {
+                streamed = true;
+                on_chunk(chunk);
+            })
+            .await;
+        // Queued requests may go ahead while this one waits to retry
+        drop(permit);
+
+        let err = match result {
+            Ok(translation) => return Ok(translation),
+            Err(e) => crate::http::explain_tls_error(e),
+        };
+        // Once text is on screen a retry would repeat it
+        if streamed || attempt >= max_retries {
+            return Err(err);
+        }
+        let Some(delay) = retry_delay(&err, attempt) else { return Err(err) };
+
+        attempt += 1;
+        logger::log(&format!(
+            "{} failed ({}), retry {}/{} in {:.1}s",
+            provider.info().short_label,
+            err,
+            attempt,
+            max_retries,
+            delay.as_secs_f32()
+        ));
+        let mut remaining = delay;
+        while !remaining.is_zero() {
+            on_wait(remaining.as_secs_f32().ceil() as u64);
+            let step = remaining.min(Duration::from_secs(1));
+            tokio::time::sleep(step).await;
+            remaining -= step;
+        }
+        on_wait(0);
+    }
+}
+
+/// How long to wait before retrying after `err`, or `None` if retrying
+/// cannot help or the server asks for more than [`MAX_WAIT`].
+fn retry_delay(err: &anyhow::Error, attempt: u32) -> Option<Duration> {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<ApiError>() {
+            if !matches!(e.status.as_u16(), 429 | 502 | 503 | 504) {
+                return None;
+            }
+            return Some(e.retry_after.unwrap_or_else(|| backoff(attempt))).filter(|&delay| delay <= MAX_WAIT);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            // Connection refused or reset; timeouts already waited long enough
+            if e.is_connect() || e.is_request() || e.is_body() {
+                return Some(backoff(attempt));
+            }
+            return None;
+        }
+    }
+    None
+}
+
+/// Exponential backoff from one second, with the delay drawn from the upper
+/// half of the window so parallel clients spread out.
+fn backoff(attempt: u32) -> Duration {
+    let window = 1000u64 << attempt.min(5);
+    let jitter = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos() as u64;
+    Duration::from_millis(window / 2 + jitter % (window / 2 + 1))
+}
+
+/// Server-suggested wait from `Retry-After` (seconds or an HTTP date), or
+/// else from the OpenAI-style reset header of the limit that ran out:
+/// `x-ratelimit-reset-requests` when `x-ratelimit-remaining-requests` is 0,
+/// `x-ratelimit-reset-tokens` when `x-ratelimit-remaining-tokens` is. A reset
+/// header alone says when the whole window refills, which is usually far
+/// later than the next request fits.
+pub(super) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return Some(Duration::from_secs_f64(secs.max(0.0)));
+        }
+        if let Ok(date) = httpdate::parse_http_date(value) {
+            return Some(date.duration_since(SystemTime::now()).unwrap_or_default());
+        }
+    }
+
+    ["requests", "tokens"]
+        .iter()
+        .filter(|limit| header(&format!("x-ratelimit-remaining-{}", limit)) == Some("0"))
+        .filter_map(|limit| header(&format!("x-ratelimit-reset-{}", limit)).and_then(parse_reset_duration))
+        .max()
+}
+
+/// Parse durations like "1s", "6m0s", "1h30m" or "250ms".
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        total += number
+            * match &rest[..unit_len] {
+                "ms" => 0.001,
+                "s" => 1.0,
+                "m" => 60.0,
+                "h" => 3600.0,
+                _ => return None,
+            };
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    fn rate_limited(retry_after: Option<Duration>) -> anyhow::Error {
+        ApiError { status: reqwest::StatusCode::TOO_MANY_REQUESTS, body: String::new(), retry_after }.into()
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(retry_after(&headers(&[("retry-after", "7")])), Some(Duration::from_secs(7)));
+        assert_eq!(retry_after(&headers(&[("retry-after", "0.5")])), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        let wait = retry_after(&headers(&[("retry-after", &date)])).unwrap();
+        // The date has whole seconds
+        assert!(wait > Duration::from_secs(28) && wait <= Duration::from_secs(30), "{:?}", wait);
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(30));
+        assert_eq!(retry_after(&headers(&[("retry-after", &past)])), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_wins_over_reset_headers() {
+        let map = headers(&[("retry-after", "2"), ("x-ratelimit-remaining-tokens", "0"), ("x-ratelimit-reset-tokens", "6m0s")]);
+        assert_eq!(retry_after(&map), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn reset_of_the_exhausted_limit() {
+        let map = headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "1.5s"),
+            ("x-ratelimit-remaining-tokens", "15000"),
+            ("x-ratelimit-reset-tokens", "6m0s"),
+        ]);
+        assert_eq!(retry_after(&map), Some(Duration::from_millis(1500)));
+        let map = headers(&[("x-ratelimit-reset-requests", "1s"), ("x-ratelimit-reset-tokens", "6m0s")]);
+        assert_eq!(retry_after(&map), None);
+    }
+
+    #[test]
+    fn reset_durations() {
+        assert_eq!(parse_reset_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_reset_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_reset_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_reset_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_reset_duration("soon"), None);
+        assert_eq!(parse_reset_duration("5x"), None);
+    }
+
+    #[test]
+    fn waits_over_the_cap_are_not_retried() {
+        assert_eq!(retry_delay(&rate_limited(Some(Duration::from_secs(30))), 0), Some(Duration::from_secs(30)));
+        assert_eq!(retry_delay(&rate_limited(Some(MAX_WAIT + Duration::from_secs(1))), 0), None);
+        // Backoff stays under the cap however many attempts failed
+        assert!(retry_delay(&rate_limited(None), 10).is_some_and(|delay| delay <= MAX_WAIT));
+    }
+}
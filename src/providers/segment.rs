@@ -0,0 +1,335 @@
+//! Splitting long texts into segments that fit a backend's output limit, and
+//! translating them in order.
+
+use super::{Endpoint, ProviderInfo, Translation, TranslationRequest};
+use futures_util::{StreamExt, TryStreamExt};
+use std::sync::Mutex;
+
+/// A piece of the source text, and the whitespace that followed it there.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    pub separator: String,
+}
+
+/// Rough token count without a tokenizer: about four characters per token
+/// for alphabetic scripts and one per CJK character.
+pub fn estimate_tokens(text: &str) -> usize {
+    let wide = text.chars().filter(|&c| c as u32 >= 0x2E80).count();
+    let narrow = text.chars().count() - wide;
+    wide + narrow.div_ceil(4)
+}
+
+/// Split `text` into segments of at most `budget` tokens, breaking between
+/// paragraphs where possible, then between lines, then between sentences,
+/// and only as a last resort inside a sentence. Consecutive small pieces are
+/// packed together.
+pub fn split(text: &str, budget: usize) -> Vec<Segment> {
+    let text = text.trim();
+    let budget = budget.max(1);
+    if estimate_tokens(text) <= budget {
+        return vec![Segment { text: text.to_string(), separator: String::new() }];
+    }
+
+    let mut segments = Vec::new();
+    let mut current = Segment::default();
+    for piece in pieces(text, budget, 0) {
+        if !current.text.is_empty()
+            && estimate_tokens(&current.text) + estimate_tokens(&current.separator) + estimate_tokens(&piece.text) > budget
+        {
+            segments.push(std::mem::take(&mut current));
+        }
+        if !current.text.is_empty() {
+            current.text.push_str(&current.separator);
+        }
+        current.text.push_str(&piece.text);
+        current.separator = piece.separator;
+    }
+    if !current.text.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+/// Break `text` at `level` (paragraphs, lines, sentences, words) and go one
+/// level finer for parts that are still over budget.
+fn pieces(text: &str, budget: usize, level: usize) -> Vec<Segment> {
+    if estimate_tokens(text) <= budget {
+        return vec![Segment { text: text.to_string(), separator: String::new() }];
+    }
+    let parts = match level {
+        0 => split_after(text, |rest| blank_line_len(rest).map(|len| (0, len))),
+        1 => split_after(text, |rest| rest.starts_with('\n').then_some((0, 1))),
+        2 => split_after(text, |rest| sentence_end_len(rest).map(|len| (len, 0))),
+        _ => return hard_split(text, budget),
+    };
+    parts
+        .into_iter()
+        .flat_map(|part| {
+            let mut sub = pieces(&part.text, budget, level + 1);
+            if let Some(last) = sub.last_mut() {
+                last.separator = part.separator;
+            }
+            sub
+        })
+        .collect()
+}
+
+/// Split wherever `boundary` matches. It returns how many bytes end the part
+/// (sentence punctuation) and how many more separate it from the next one
+/// (line breaks); whitespace after that is part of the separator too.
+fn split_after(text: &str, boundary: impl Fn(&str) -> Option<(usize, usize)>) -> Vec<Segment> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        match boundary(rest) {
+            Some((keep, skip)) if keep + skip > 0 => {
+                let content = text[start..i + keep].trim_end();
+                let after = &rest[keep + skip..];
+                let sep_end = i + keep + skip + (after.len() - after.trim_start().len());
+                let separator = &text[start + content.len()..sep_end];
+                if !content.is_empty() {
+                    parts.push(Segment { text: content.to_string(), separator: separator.to_string() });
+                }
+                start = sep_end;
+                i = sep_end;
+            }
+            _ => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if start < text.len() {
+        parts.push(Segment { text: text[start..].to_string(), separator: String::new() });
+    }
+    parts
+}
+
+/// Length of a blank line (two line breaks with only spaces between) at the
+/// start of `rest`.
+fn blank_line_len(rest: &str) -> Option<usize> {
+    let after = rest.strip_prefix('\n')?;
+    let spaces = after.len() - after.trim_start_matches([' ', '\t', '\r']).len();
+    after[spaces..].starts_with('\n').then_some(1 + spaces + 1)
+}
+
+/// Length of a sentence end at the start of `rest`: the punctuation itself,
+/// followed by whitespace for Western punctuation.
+fn sentence_end_len(rest: &str) -> Option<usize> {
+    let c = rest.chars().next()?;
+    match c {
+        '。' | '！' | '？' | '…' => Some(c.len_utf8()),
+        '.' | '!' | '?' if rest[1..].starts_with(char::is_whitespace) => Some(1),
+        _ => None,
+    }
+}
+
+/// Cut a sentence that alone is over budget, preferring to cut at spaces.
+fn hard_split(text: &str, budget: usize) -> Vec<Segment> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = rest.len();
+        let mut tokens = 0;
+        for (i, c) in rest.char_indices() {
+            tokens += if c as u32 >= 0x2E80 { 4 } else { 1 };
+            if tokens > budget * 4 {
+                end = i.max(c.len_utf8());
+                break;
+            }
+        }
+        if end < rest.len() {
+            if let Some(space) = rest[..end].rfind(' ').filter(|&s| s > 0) {
+                end = space;
+            }
+        }
+        let (part, tail) = rest.split_at(end);
+        let trimmed = tail.trim_start();
+        parts.push(Segment { text: part.to_string(), separator: tail[..tail.len() - trimmed.len()].to_string() });
+        rest = trimmed;
+    }
+    parts
+}
+
+/// Callbacks for [`translate_segments`].
+pub struct SegmentOutput<'a> {
+    /// A backend starts on a segment
+    pub on_attempt: &'a (dyn Fn(&'static ProviderInfo) + Sync),
+    /// More text, in source order
+    pub on_chunk: &'a (dyn Fn(String) + Sync),
+    /// Everything shown so far must be replaced by this text
+    pub on_reset: &'a (dyn Fn(String) + Sync),
+    /// Segments done out of the total
+    pub on_progress: &'a (dyn Fn(usize, usize) + Sync),
+    /// Seconds until a retry, see [`super::translate_with_fallback`]
+    pub on_wait: &'a (dyn Fn(u64) + Sync),
+}
+
+/// Output of the segments received so far. Only the first unfinished
+/// segment streams to the window; later ones are held back until it is done.
+struct Assembly {
+    texts: Vec<String>,
+    done: Vec<bool>,
+    /// First segment not yet finished
+    head: usize,
+    finished: usize,
+}
+
+impl Assembly {
+    /// What the window shows: every finished segment before the head, then
+    /// the head's partial text.
+    fn shown(&self, segments: &[Segment]) -> String {
+        let mut text = String::new();
+        for (done, segment) in self.texts.iter().zip(segments).take(self.head) {
+            text.push_str(done);
+            text.push_str(&segment.separator);
+        }
+        if self.head < self.texts.len() {
+            text.push_str(&self.texts[self.head]);
+        }
+        text
+    }
+}
+
+/// Translate `segments` with the fallback chain, up to `concurrency` at a
+/// time, streaming the output in source order. Returns the joined translation,
+/// truncated if any segment was and untranslated only if all were, and the
+/// backend that translated the first segment.
+pub async fn translate_segments(
+    chain: &[Endpoint],
+    req: &TranslationRequest,
+    segments: &[Segment],
+    concurrency: usize,
+    out: &SegmentOutput<'_>,
+) -> anyhow::Result<(Translation, &'static ProviderInfo)> {
+    let total = segments.len();
+    let assembly = Mutex::new(Assembly { texts: vec![String::new(); total], done: vec![false; total], head: 0, finished: 0 });
+    (out.on_progress)(0, total);
+
+    let assembly = &assembly;
+    let jobs = segments.iter().enumerate().map(|(i, segment)| async move {
+        let seg_req = TranslationRequest {
+            text: segment.text.clone(),
+            image: None,
+            target_lang: req.target_lang.clone(),
+            max_tokens: req.max_tokens,
+        };
+        let mut attempts = 0;
+        let (translation, info) = super::translate_with_fallback(
+            chain,
+            &seg_req,
+            &mut |info| {
+                // Drop partial output from the backend that just failed
+                if attempts > 0 {
+                    let mut a = assembly.lock().unwrap();
+                    a.texts[i].clear();
+                    if a.head == i {
+                        (out.on_reset)(a.shown(segments));
+                    }
+                }
+                attempts += 1;
+                (out.on_attempt)(info);
+            },
+            &mut |chunk| {
+                let mut a = assembly.lock().unwrap();
+                a.texts[i].push_str(&chunk);
+                if a.head == i {
+                    (out.on_chunk)(chunk);
+                }
+            },
+            &mut |secs| (out.on_wait)(secs),
+        )
+        .await?;
+
+        let text = &translation.text;
+        let mut a = assembly.lock().unwrap();
+        let streamed = std::mem::replace(&mut a.texts[i], text.clone());
+        a.done[i] = true;
+        a.finished += 1;
+        (out.on_progress)(a.finished, total);
+        if a.head == i {
+            if streamed != *text {
+                (out.on_reset)(a.shown(segments));
+            }
+            // Release the segments that finished while waiting for this one
+            while a.head < total && a.done[a.head] {
+                (out.on_chunk)(segments[a.head].separator.clone());
+                a.head += 1;
+                if a.head < total {
+                    (out.on_chunk)(a.texts[a.head].clone());
+                }
+            }
+        }
+        anyhow::Ok((translation, info))
+    });
+
+    let results: Vec<(Translation, &'static ProviderInfo)> =
+        futures_util::stream::iter(jobs).buffered(concurrency.max(1)).try_collect().await?;
+
+    let mut joined = String::new();
+    for ((translation, _), segment) in results.iter().zip(segments) {
+        joined.push_str(&translation.text);
+        joined.push_str(&segment.separator);
+    }
+    let translation = Translation {
+        text: joined.trim_end().to_string(),
+        truncated: results.iter().any(|(translation, _)| translation.truncated),
+        source_lang: results.iter().find_map(|(translation, _)| translation.source_lang.clone()),
+        untranslated: results.iter().all(|(translation, _)| translation.untranslated),
+    };
+    Ok((translation, results[0].1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejoin(segments: &[Segment]) -> String {
+        segments.iter().map(|s| format!("{}{}", s.text, s.separator)).collect()
+    }
+
+    #[test]
+    fn short_text_is_one_segment() {
+        assert_eq!(split("  Hello world.  ", 100), vec![Segment { text: "Hello world.".into(), separator: String::new() }]);
+    }
+
+    #[test]
+    fn paragraphs_are_packed_within_the_budget() {
+        let para = "word ".repeat(20).trim_end().to_string(); // 25 tokens
+        let text = [para.as_str(); 5].join("\n\n");
+        let segments = split(&text, 60);
+        assert_eq!(segments.len(), 3);
+        assert!(segments.iter().all(|s| estimate_tokens(&s.text) <= 60));
+        assert_eq!(segments[0].text, format!("{}\n\n{}", para, para));
+        assert_eq!(segments[0].separator, "\n\n");
+        assert_eq!(rejoin(&segments), text);
+    }
+
+    #[test]
+    fn long_paragraph_breaks_between_sentences() {
+        let sentence = "This sentence has exactly forty characters.";
+        let text = [sentence; 6].join(" ");
+        let segments = split(&text, 25);
+        assert!(segments.len() > 1);
+        assert!(segments.iter().all(|s| s.text.ends_with('.')));
+        assert_eq!(rejoin(&segments), text);
+    }
+
+    #[test]
+    fn cjk_sentences_split_without_spaces() {
+        let text = "这是第一句话，内容比较长一些。".repeat(10);
+        let segments = split(&text, 40);
+        assert!(segments.len() > 1);
+        assert!(segments.iter().all(|s| s.text.ends_with('。') && estimate_tokens(&s.text) <= 40));
+        assert_eq!(rejoin(&segments), text);
+    }
+
+    #[test]
+    fn oversized_sentence_is_cut_at_spaces() {
+        let text = "lorem ipsum ".repeat(100);
+        let segments = split(&text, 20);
+        assert!(segments.iter().all(|s| estimate_tokens(&s.text) <= 20));
+        assert!(segments.iter().all(|s| !s.text.starts_with(' ') && !s.text.ends_with(' ')));
+        assert_eq!(rejoin(&segments), text.trim());
+    }
+}
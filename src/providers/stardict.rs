@@ -0,0 +1,566 @@
+//! Offline word lookup in locally installed StarDict dictionaries
+//! (`.ifo` + `.idx` + `.dict` or dictzip-compressed `.dict.dz`).
+//!
+//! This is not a translation backend: the hotkey worker tries it first for
+//! single words and only calls the configured backend when nothing is found.
+
+use super::{DictEntry, Sense};
+use crate::logger;
+use once_cell::sync::Lazy;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Dictionaries loaded from the configured directories, reloaded when the
+/// directory list changes.
+static LOADED: Lazy<Mutex<(Vec<String>, Vec<StarDict>)>> = Lazy::new(|| Mutex::new((Vec::new(), Vec::new())));
+
+/// Look `word` up in every dictionary found under `dirs`.
+pub fn lookup(dirs: &[String], word: &str) -> Vec<DictEntry> {
+    let mut loaded = LOADED.lock().unwrap();
+    if loaded.0 != dirs {
+        let dicts = load_dictionaries(dirs);
+        logger::log(&format!("StarDict: loaded {} dictionaries", dicts.len()));
+        *loaded = (dirs.to_vec(), dicts);
+    }
+
+    let mut entries = Vec::new();
+    for dict in &mut loaded.1 {
+        match dict.lookup(word) {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {}
+            Err(e) => logger::log(&format!("StarDict: lookup in {} failed: {}", dict.name, e)),
+        }
+    }
+    entries
+}
+
+fn load_dictionaries(dirs: &[String]) -> Vec<StarDict> {
+    let mut ifo_files = Vec::new();
+    for dir in dirs {
+        find_ifo_files(Path::new(dir), &mut ifo_files);
+    }
+    ifo_files.sort();
+
+    ifo_files
+        .iter()
+        .filter_map(|ifo| match StarDict::open(ifo) {
+            Ok(dict) => Some(dict),
+            Err(e) => {
+                logger::log(&format!("StarDict: skipping {}: {}", ifo.display(), e));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Dictionaries usually live one folder deep (`dic/<name>/<name>.ifo`).
+fn find_ifo_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(read_dir) = fs::read_dir(dir) else { return };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_ifo_files(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "ifo") {
+            out.push(path);
+        }
+    }
+}
+
+struct IndexEntry {
+    word: String,
+    offset: u64,
+    size: u32,
+}
+
+struct StarDict {
+    name: String,
+    /// Field types of every entry when the `.ifo` declares them once
+    same_type_sequence: Option<String>,
+    /// Sorted the StarDict way: ASCII-case-insensitive, then byte order
+    index: Vec<IndexEntry>,
+    data: DictData,
+}
+
+impl StarDict {
+    fn open(ifo_path: &Path) -> anyhow::Result<Self> {
+        let ifo = fs::read_to_string(ifo_path)?;
+        let mut lines = ifo.lines();
+        if lines.next().map(str::trim) != Some("StarDict's dict ifo file") {
+            anyhow::bail!("not a StarDict .ifo file");
+        }
+
+        let mut name = String::new();
+        let mut same_type_sequence = None;
+        let mut offset_bits = 32;
+        for line in lines {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key.trim() {
+                "bookname" => name = value.trim().to_string(),
+                "sametypesequence" => same_type_sequence = Some(value.trim().to_string()),
+                "idxoffsetbits" => offset_bits = value.trim().parse().unwrap_or(32),
+                _ => {}
+            }
+        }
+
+        let base = ifo_path.with_extension("");
+        let index = read_index(&base, offset_bits)?;
+        let data = DictData::open(&base)?;
+        if name.is_empty() {
+            name = base.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        }
+
+        Ok(Self { name, same_type_sequence, index, data })
+    }
+
+    fn lookup(&mut self, word: &str) -> anyhow::Result<Option<DictEntry>> {
+        let key = word.to_ascii_lowercase();
+        let start = self.index.partition_point(|e| e.word.to_ascii_lowercase() < key);
+        let matches: Vec<usize> = (start..self.index.len())
+            .take_while(|&i| self.index[i].word.to_ascii_lowercase() == key)
+            .collect();
+        // Prefer the exact spelling ("Polish" vs "polish") when both exist
+        let Some(&i) = matches.iter().find(|&&i| self.index[i].word == word).or(matches.first()) else {
+            return Ok(None);
+        };
+
+        let headword = self.index[i].word.clone();
+        let raw = self.data.read(self.index[i].offset, self.index[i].size)?;
+        let (pronunciation, meanings) = parse_fields(&raw, self.same_type_sequence.as_deref());
+        if meanings.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(DictEntry {
+            headword,
+            pronunciation,
+            translation: meanings[0].clone(),
+            senses: vec![Sense { part_of_speech: String::new(), meanings }],
+            source: self.name.clone(),
+            source_lang: None,
+        }))
+    }
+}
+
+/// Read `<base>.idx` (or `<base>.idx.gz`): a NUL-terminated word followed
+/// by a big-endian offset (32 or 64 bits) and size into the `.dict` file.
+fn read_index(base: &Path, offset_bits: u32) -> anyhow::Result<Vec<IndexEntry>> {
+    let plain = base.with_extension("idx");
+    let bytes = if plain.exists() {
+        fs::read(&plain)?
+    } else {
+        let mut bytes = Vec::new();
+        flate2::read::GzDecoder::new(File::open(base.with_extension("idx.gz"))?).read_to_end(&mut bytes)?;
+        bytes
+    };
+
+    let offset_len = if offset_bits == 64 { 8 } else { 4 };
+    let mut index = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let Some(nul) = bytes[pos..].iter().position(|&b| b == 0) else { break };
+        let word = String::from_utf8_lossy(&bytes[pos..pos + nul]).into_owned();
+        pos += nul + 1;
+        if pos + offset_len + 4 > bytes.len() {
+            anyhow::bail!("truncated .idx file");
+        }
+        let offset = bytes[pos..pos + offset_len].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        pos += offset_len;
+        let size = u32::from_be_bytes(bytes[pos..pos + 4].try_into()?);
+        pos += 4;
+        index.push(IndexEntry { word, offset, size });
+    }
+    Ok(index)
+}
+
+/// The `.dict` payload, either plain or dictzip-compressed.
+enum DictData {
+    Plain(File),
+    DictZip(DictZip),
+}
+
+impl DictData {
+    fn open(base: &Path) -> anyhow::Result<Self> {
+        let plain = base.with_extension("dict");
+        if plain.exists() {
+            return Ok(Self::Plain(File::open(plain)?));
+        }
+        Ok(Self::DictZip(DictZip::open(&base.with_extension("dict.dz"))?))
+    }
+
+    fn read(&mut self, offset: u64, size: u32) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Plain(file) => {
+                let mut buf = vec![0; size as usize];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut buf)?;
+                Ok(buf)
+            }
+            Self::DictZip(dz) => dz.read(offset, size as usize),
+        }
+    }
+}
+
+/// dictzip is gzip with an extra "RA" header field listing independently
+/// compressed chunks, so an entry can be read without inflating the whole
+/// file. Plain gzip files (no "RA" field) are inflated once into memory.
+enum DictZip {
+    Chunked {
+        file: File,
+        chunk_len: usize,
+        /// File offset and compressed size of every chunk
+        chunks: Vec<(u64, usize)>,
+    },
+    Inflated(Vec<u8>),
+}
+
+impl DictZip {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut header = [0u8; 10];
+        file.read_exact(&mut header)?;
+        if header[..3] != [0x1f, 0x8b, 8] {
+            anyhow::bail!("{} is not gzip-compressed", path.display());
+        }
+        let flags = header[3];
+
+        let mut ra = None;
+        let mut data_start = 10u64;
+        if flags & 0x04 != 0 {
+            // FEXTRA: subfields of (id, length, data)
+            let mut len = [0u8; 2];
+            file.read_exact(&mut len)?;
+            let mut extra = vec![0u8; u16::from_le_bytes(len) as usize];
+            file.read_exact(&mut extra)?;
+            data_start += 2 + extra.len() as u64;
+
+            let mut pos = 0;
+            while pos + 4 <= extra.len() {
+                let sub_len = u16::from_le_bytes([extra[pos + 2], extra[pos + 3]]) as usize;
+                let sub = extra.get(pos + 4..pos + 4 + sub_len).unwrap_or_default();
+                if &extra[pos..pos + 2] == b"RA" {
+                    ra = Some(sub.to_vec());
+                }
+                pos += 4 + sub_len;
+            }
+        }
+        // FNAME and FCOMMENT are NUL-terminated strings
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                let mut byte = [0u8; 1];
+                loop {
+                    file.read_exact(&mut byte)?;
+                    data_start += 1;
+                    if byte[0] == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+        if flags & 0x02 != 0 {
+            data_start += 2; // FHCRC
+        }
+
+        // RA: version, chunk length, chunk count, then each compressed size
+        let field = |ra: &[u8], i: usize| ra.get(i * 2..i * 2 + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+        if let Some(ra) = ra.filter(|ra| field(ra, 0) == Some(1)) {
+            let chunk_len = field(&ra, 1).unwrap_or_default();
+            let count = field(&ra, 2).unwrap_or_default();
+            if chunk_len == 0 || count == 0 {
+                anyhow::bail!("{} has an empty dictzip chunk table", path.display());
+            }
+            let mut chunks = Vec::with_capacity(count);
+            let mut offset = data_start;
+            for i in 0..count {
+                let size = field(&ra, 3 + i).ok_or_else(|| anyhow::anyhow!("truncated dictzip header"))?;
+                chunks.push((offset, size));
+                offset += size as u64;
+            }
+            return Ok(Self::Chunked { file, chunk_len, chunks });
+        }
+
+        let mut inflated = Vec::new();
+        flate2::read::MultiGzDecoder::new(File::open(path)?).read_to_end(&mut inflated)?;
+        Ok(Self::Inflated(inflated))
+    }
+
+    fn read(&mut self, offset: u64, size: usize) -> anyhow::Result<Vec<u8>> {
+        let offset = offset as usize;
+        match self {
+            Self::Inflated(data) => data
+                .get(offset..offset + size)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| anyhow::anyhow!("entry outside of dictionary data")),
+            Self::Chunked { file, chunk_len, chunks } => {
+                let first = offset / *chunk_len;
+                let last = (offset + size).saturating_sub(1) / *chunk_len;
+                let mut out = Vec::with_capacity(size);
+                for i in first..=last {
+                    let &(chunk_offset, chunk_size) =
+                        chunks.get(i).ok_or_else(|| anyhow::anyhow!("entry outside of dictionary data"))?;
+                    let mut compressed = vec![0u8; chunk_size];
+                    file.seek(SeekFrom::Start(chunk_offset))?;
+                    file.read_exact(&mut compressed)?;
+
+                    // Every chunk is flushed on its own and inflates independently
+                    let mut chunk = Vec::with_capacity(*chunk_len);
+                    flate2::Decompress::new(false).decompress_vec(
+                        &compressed,
+                        &mut chunk,
+                        flate2::FlushDecompress::Sync,
+                    )?;
+
+                    let chunk_start = i * *chunk_len;
+                    let from = offset.saturating_sub(chunk_start).min(chunk.len());
+                    let to = (offset + size - chunk_start).min(chunk.len());
+                    out.extend_from_slice(&chunk[from..to]);
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Split an entry into its pronunciation and meaning lines. Fields are typed
+/// by a single letter: lowercase types are text, uppercase types are binary
+/// with a 32-bit size prefix. With `sametypesequence` the type letters are
+/// omitted and the last field runs to the end of the entry.
+fn parse_fields(raw: &[u8], same_type_sequence: Option<&str>) -> (Option<String>, Vec<String>) {
+    let mut pronunciation = None;
+    let mut meanings = Vec::new();
+    let mut pos = 0;
+    let types: Vec<u8> = same_type_sequence.map(|s| s.bytes().collect()).unwrap_or_default();
+    let mut field_index = 0;
+
+    while pos < raw.len() {
+        let (kind, is_last) = if types.is_empty() {
+            let kind = raw[pos];
+            pos += 1;
+            (kind, false)
+        } else {
+            let Some(&kind) = types.get(field_index) else { break };
+            (kind, field_index + 1 == types.len())
+        };
+        field_index += 1;
+
+        let value = if kind.is_ascii_lowercase() {
+            let end = if is_last {
+                raw.len()
+            } else {
+                raw[pos..].iter().position(|&b| b == 0).map_or(raw.len(), |n| pos + n)
+            };
+            let text = String::from_utf8_lossy(&raw[pos..end]).into_owned();
+            pos = end + 1;
+            text
+        } else {
+            // Binary resources (sounds, pictures) are skipped
+            let size = if is_last {
+                raw.len() - pos
+            } else if pos + 4 <= raw.len() {
+                let size = u32::from_be_bytes([raw[pos], raw[pos + 1], raw[pos + 2], raw[pos + 3]]) as usize;
+                pos += 4;
+                size
+            } else {
+                break;
+            };
+            pos += size;
+            continue;
+        };
+
+        match kind {
+            b't' | b'y' => pronunciation = Some(value.trim().to_string()),
+            b'm' | b'l' => meanings.extend(non_empty_lines(&value)),
+            b'g' | b'h' | b'x' | b'k' | b'w' | b'n' => meanings.extend(non_empty_lines(&strip_markup(&value))),
+            _ => {}
+        }
+    }
+
+    (pronunciation.filter(|p| !p.is_empty()), meanings)
+}
+
+fn non_empty_lines(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string)
+}
+
+/// Rough conversion of Pango/HTML/XDXF markup to plain text.
+fn strip_markup(markup: &str) -> String {
+    let mut out = String::with_capacity(markup.len());
+    let mut rest = markup;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('>') else {
+            rest = &rest[open..];
+            break;
+        };
+        let tag = rest[open + 1..open + close].trim_start_matches('/').to_ascii_lowercase();
+        if ["br", "p", "div", "li", "tr", "def", "blockquote"].iter().any(|t| tag.starts_with(t)) {
+            out.push('\n');
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// An empty directory for one test's files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("echo-stardict-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn idx_entry(word: &str, offset: u64, size: u32, offset_bits: u32) -> Vec<u8> {
+        let mut bytes = word.as_bytes().to_vec();
+        bytes.push(0);
+        if offset_bits == 64 {
+            bytes.extend_from_slice(&offset.to_be_bytes());
+        } else {
+            bytes.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+        bytes.extend_from_slice(&size.to_be_bytes());
+        bytes
+    }
+
+    /// `data` compressed as dictzip, in chunks of `chunk_len` bytes.
+    fn dictzip(data: &[u8], chunk_len: usize) -> Vec<u8> {
+        let chunks: Vec<Vec<u8>> = data
+            .chunks(chunk_len)
+            .map(|piece| {
+                let mut compressed = Vec::with_capacity(piece.len() + 64);
+                flate2::Compress::new(flate2::Compression::default(), false)
+                    .compress_vec(piece, &mut compressed, flate2::FlushCompress::Sync)
+                    .unwrap();
+                compressed
+            })
+            .collect();
+
+        let mut ra = Vec::new();
+        for value in [1, chunk_len, chunks.len()].into_iter().chain(chunks.iter().map(Vec::len)) {
+            ra.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        let mut file = vec![0x1f, 0x8b, 8, 0x04, 0, 0, 0, 0, 0, 3];
+        file.extend_from_slice(&(ra.len() as u16 + 4).to_le_bytes());
+        file.extend_from_slice(b"RA");
+        file.extend_from_slice(&(ra.len() as u16).to_le_bytes());
+        file.extend_from_slice(&ra);
+        for chunk in &chunks {
+            file.extend_from_slice(chunk);
+        }
+        file
+    }
+
+    #[test]
+    fn index_entries_with_32_and_64_bit_offsets() {
+        let dir = temp_dir("index");
+        for bits in [32, 64] {
+            let base = dir.join(format!("dict{}", bits));
+            let mut idx = idx_entry("apple", 0, 12, bits);
+            idx.extend(idx_entry("bank", 12, 30, bits));
+            fs::write(base.with_extension("idx"), idx).unwrap();
+
+            let index = read_index(&base, bits).unwrap();
+            let entries: Vec<(&str, u64, u32)> = index.iter().map(|e| (e.word.as_str(), e.offset, e.size)).collect();
+            assert_eq!(entries, [("apple", 0, 12), ("bank", 12, 30)]);
+        }
+
+        let base = dir.join("truncated");
+        let mut idx = idx_entry("apple", 0, 12, 32);
+        idx.truncate(idx.len() - 2);
+        fs::write(base.with_extension("idx"), idx).unwrap();
+        assert!(read_index(&base, 32).is_err());
+    }
+
+    #[test]
+    fn fields_with_a_same_type_sequence() {
+        let (pronunciation, meanings) = parse_fields("ˈæpəl\0n. 苹果\n\nn. 苹果树".as_bytes(), Some("tm"));
+        assert_eq!(pronunciation.as_deref(), Some("ˈæpəl"));
+        assert_eq!(meanings, ["n. 苹果", "n. 苹果树"]);
+    }
+
+    #[test]
+    fn typed_fields_skip_binary_resources() {
+        let mut raw = b"t/b\xc3\xa6\xc5\x8bk/\0".to_vec();
+        raw.push(b'W');
+        raw.extend_from_slice(&3u32.to_be_bytes());
+        raw.extend_from_slice(b"\x00\x01\x02");
+        raw.extend_from_slice("h<b>银行</b><br>河岸\0".as_bytes());
+        let (pronunciation, meanings) = parse_fields(&raw, None);
+        assert_eq!(pronunciation.as_deref(), Some("/bæŋk/"));
+        assert_eq!(meanings, ["银行", "河岸"]);
+    }
+
+    #[test]
+    fn markup_becomes_plain_lines() {
+        assert_eq!(strip_markup("<b>bank</b><br/>river &amp; money<div>x &lt; y</div>"), "bank\nriver & money\nx < y\n");
+        assert_eq!(strip_markup("a < b"), "a < b");
+    }
+
+    #[test]
+    fn dictzip_entries_across_chunks() {
+        let dir = temp_dir("dictzip");
+        let data: Vec<u8> = (0..200u8).collect();
+        let path = dir.join("test.dict.dz");
+        fs::write(&path, dictzip(&data, 64)).unwrap();
+
+        let mut dz = DictZip::open(&path).unwrap();
+        assert!(matches!(dz, DictZip::Chunked { chunk_len: 64, .. }));
+        assert_eq!(dz.read(0, 10).unwrap(), data[..10]);
+        // Spans the second, third and fourth chunk
+        assert_eq!(dz.read(60, 100).unwrap(), data[60..160]);
+        assert_eq!(dz.read(190, 10).unwrap(), data[190..]);
+        assert!(dz.read(250, 10).is_err());
+    }
+
+    #[test]
+    fn dictzip_without_chunks_is_rejected() {
+        let dir = temp_dir("empty-dictzip");
+        let path = dir.join("test.dict.dz");
+        // The RA field starts at byte 16 with its version, chunk length and count
+        for field in [18, 20] {
+            let mut file = dictzip(b"abc", 64);
+            file[field..field + 2].copy_from_slice(&0u16.to_le_bytes());
+            fs::write(&path, file).unwrap();
+            let err = DictZip::open(&path).err().expect("an error");
+            assert!(err.to_string().contains("empty dictzip chunk table"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn lookup_in_a_compressed_dictionary() {
+        let dir = temp_dir("lookup");
+        let definitions = ["n. 苹果", "n. 银行\nn. 河岸", "v. 银行存款"];
+        let words = ["apple", "Bank", "bank"];
+        let mut data = Vec::new();
+        let mut idx = Vec::new();
+        for (word, definition) in words.iter().zip(definitions) {
+            idx.extend(idx_entry(word, data.len() as u64, definition.len() as u32, 32));
+            data.extend_from_slice(definition.as_bytes());
+        }
+        let base = dir.join("test");
+        fs::write(base.with_extension("idx"), idx).unwrap();
+        fs::write(base.with_extension("dict.dz"), dictzip(&data, 16)).unwrap();
+        let mut ifo = File::create(base.with_extension("ifo")).unwrap();
+        writeln!(ifo, "StarDict's dict ifo file\nversion=2.4.2\nwordcount=3\nbookname=Test\nsametypesequence=m").unwrap();
+
+        let mut dict = StarDict::open(&base.with_extension("ifo")).unwrap();
+        let entry = dict.lookup("bank").unwrap().unwrap();
+        assert_eq!((entry.headword.as_str(), entry.source.as_str()), ("bank", "Test"));
+        assert_eq!(entry.translation, "v. 银行存款");
+        // Case-insensitive when the exact spelling is missing
+        assert_eq!(dict.lookup("APPLE").unwrap().unwrap().senses[0].meanings, ["n. 苹果"]);
+        assert!(dict.lookup("cherry").unwrap().is_none());
+    }
+}
@@ -0,0 +1,218 @@
+//! Recorded HTTP responses for backend tests.
+//!
+//! A test starts a [`Server`] on a local port and points a backend at it. The
+//! server answers every request from a fixture in `tests/fixtures`, sending
+//! the body in the fixture's chunks, so parsers see lines and characters
+//! split across network reads. With `ECHO_RECORD=1` the first request is
+//! forwarded to the real `upstream` instead and the answer written to the
+//! fixture, e.g.
+//!
+//! ```text
+//! ECHO_RECORD=1 OPENAI_API_KEY=sk-... cargo test openai
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A recorded response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Written by hand in the shape of the service's answers rather than
+    /// recorded from it; recording replaces it with a real answer
+    #[serde(default)]
+    pub synthetic: bool,
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    /// Byte offsets in `body` where each chunk ended; they may fall inside a
+    /// character
+    pub chunks: Vec<usize>,
+}
+
+impl Fixture {
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(format!("{}.json", name))
+    }
+
+    fn load(name: &str) -> Self {
+        let path = Self::path(name);
+        let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        serde_json::from_str(&json).unwrap_or_else(|e| panic!("invalid fixture {}: {}", path.display(), e))
+    }
+
+    fn save(&self, name: &str) {
+        let path = Self::path(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_string_pretty(self).unwrap() + "\n").unwrap();
+    }
+
+    /// The body cut at the recorded chunk boundaries.
+    fn body_chunks(&self) -> Vec<&[u8]> {
+        let body = self.body.as_bytes();
+        let mut start = 0;
+        let mut chunks = Vec::new();
+        for &end in self.chunks.iter().chain(std::iter::once(&body.len())) {
+            let end = end.min(body.len());
+            if end > start {
+                chunks.push(&body[start..end]);
+                start = end;
+            }
+        }
+        chunks
+    }
+}
+
+/// A request the server received.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Path and query
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).expect("request body is not JSON")
+    }
+}
+
+/// Local server replaying (or recording) one fixture.
+pub struct Server {
+    /// Base URL, e.g. `http://127.0.0.1:50123`
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Server {
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Serve fixture `name`, recording it from `upstream` first when
+/// `ECHO_RECORD` is set.
+pub async fn serve(name: &'static str, upstream: &'static str) -> Server {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests: Arc<Mutex<Vec<Request>>> = Arc::default();
+    let record = std::env::var_os("ECHO_RECORD").is_some();
+    let mut fixture = if record { None } else { Some(Fixture::load(name)) };
+
+    let received = Arc::clone(&requests);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            stream.set_nodelay(true).unwrap();
+            let mut reader = BufReader::new(stream);
+            let request = read_request(&mut reader).await;
+            received.lock().unwrap().push(request.clone());
+            if fixture.is_none() {
+                let recorded = record_upstream(&request, upstream).await;
+                recorded.save(name);
+                fixture = Some(recorded);
+            }
+            write_response(reader.into_inner(), fixture.as_ref().unwrap()).await;
+        }
+    });
+    Server { url, requests }
+}
+
+async fn read_request(reader: &mut BufReader<TcpStream>) -> Request {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.unwrap();
+    Request { method, target, headers, body }
+}
+
+/// Send the fixture with chunked encoding, pausing between chunks so the
+/// client reads them one by one.
+async fn write_response(mut stream: TcpStream, fixture: &Fixture) {
+    let reason = reqwest::StatusCode::from_u16(fixture.status).ok().and_then(|s| s.canonical_reason()).unwrap_or("");
+    let head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: {}\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n",
+        fixture.status, reason, fixture.content_type
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    for chunk in fixture.body_chunks() {
+        stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await.unwrap();
+        stream.write_all(chunk).await.unwrap();
+        stream.write_all(b"\r\n").await.unwrap();
+        stream.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stream.write_all(b"0\r\n\r\n").await.unwrap();
+    let _ = stream.shutdown().await;
+}
+
+/// Forward `request` to `upstream` and record the answer as it arrives.
+async fn record_upstream(request: &Request, upstream: &str) -> Fixture {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes()).unwrap();
+    let mut forward = reqwest::Client::new().request(method, format!("{}{}", upstream.trim_end_matches('/'), request.target));
+    for (name, value) in &request.headers {
+        if !["host", "content-length", "connection"].iter().any(|skip| name.eq_ignore_ascii_case(skip)) {
+            forward = forward.header(name, value);
+        }
+    }
+    let mut resp = forward.body(request.body.clone()).send().await.expect("upstream request failed");
+
+    let status = resp.status().as_u16();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let mut body = Vec::new();
+    let mut chunks = Vec::new();
+    while let Some(chunk) = resp.chunk().await.expect("upstream response failed") {
+        body.extend_from_slice(&chunk);
+        chunks.push(body.len());
+    }
+    chunks.pop(); // The end of the body is implied
+    Fixture { synthetic: false, status, content_type, body: String::from_utf8_lossy(&body).into_owned(), chunks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_is_cut_where_recorded() {
+        let fixture = Fixture { synthetic: true, status: 200, content_type: String::new(), body: "data: 你好\n\n".to_string(), chunks: vec![7, 9] };
+        let chunks = fixture.body_chunks();
+        assert_eq!(chunks, [&b"data: \xe4"[..], &b"\xbd\xa0"[..], "好\n\n".as_bytes()]);
+    }
+}
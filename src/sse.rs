@@ -0,0 +1,231 @@
+//! Server-sent events decoder, following the WHATWG event stream format.
+//!
+//! Bytes go in as they arrive from the network, in chunks of any size;
+//! complete events come out. Handles `\n`, `\r\n` and `\r` line endings,
+//! comments, `event:`/`id:` fields, multi-line `data:` fields and UTF-8
+//! characters split across chunks.
+
+/// A dispatched event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Event type; "message" unless the server sent an `event:` field
+    pub event: String,
+    /// `data:` lines joined with '\n'
+    pub data: String,
+    /// Last event ID seen on the stream, if any
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Decoder {
+    /// Bytes of the line being received
+    line: Vec<u8>,
+    /// The previous line ended with '\r'; a '\n' right after it belongs to it
+    after_cr: bool,
+    started: bool,
+    event: String,
+    data: String,
+    has_data: bool,
+    last_id: Option<String>,
+}
+
+impl Decoder {
+    /// Feed the next chunk of the stream and collect the events it completes.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        for &b in bytes {
+            if std::mem::take(&mut self.after_cr) && b == b'\n' {
+                continue;
+            }
+            match b {
+                b'\r' | b'\n' => {
+                    self.after_cr = b == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    events.extend(self.process_line(&line));
+                }
+                _ => self.line.push(b),
+            }
+        }
+        events
+    }
+
+    /// End of stream. Strictly, an event without its terminating blank line
+    /// is discarded; plenty of servers close the connection right after the
+    /// last `data:` line, so it is dispatched instead.
+    pub fn finish(&mut self) -> Option<Event> {
+        let line = std::mem::take(&mut self.line);
+        if !line.is_empty() {
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        self.process_line(b"")
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<Event> {
+        let mut line = line;
+        if !self.started {
+            self.started = true;
+            line = line.strip_prefix("\u{feff}".as_bytes()).unwrap_or(line);
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line[0] == b':' {
+            return None; // comment, often used as a keep-alive
+        }
+
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            // `retry` is reconnection advice; requests are never resumed
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = std::mem::take(&mut self.event);
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        Some(Event {
+            event: if event.is_empty() { "message".to_string() } else { event },
+            data: std::mem::take(&mut self.data),
+            id: self.last_id.clone(),
+        })
+    }
+}
+
+/// Feed every event of a streamed response to `on_event`, which returns
+/// `Ok(true)` once the backend signalled the end of the stream. Errors from
+/// `on_event` abort the stream and are returned as-is.
+pub async fn for_each_event<F>(mut resp: reqwest::Response, mut on_event: F) -> anyhow::Result<()>
+where
+    F: FnMut(Event) -> anyhow::Result<bool>,
+{
+    let mut decoder = Decoder::default();
+    while let Some(chunk) = crate::http::next_chunk(&mut resp).await? {
+        for event in decoder.push(&chunk) {
+            if on_event(event)? {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(event) = decoder.finish() {
+        on_event(event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> Vec<Event> {
+        let mut decoder = Decoder::default();
+        let mut events: Vec<Event> = chunks.iter().flat_map(|c| decoder.push(c)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    fn message(data: &str) -> Event {
+        Event { event: "message".to_string(), data: data.to_string(), id: None }
+    }
+
+    #[test]
+    fn single_event() {
+        assert_eq!(decode_chunks(&[b"data: hello\n\n"]), vec![message("hello")]);
+    }
+
+    #[test]
+    fn multi_line_data_is_joined() {
+        assert_eq!(decode_chunks(&[b"data: one\ndata: two\ndata:\n\n"]), vec![message("one\ntwo\n")]);
+    }
+
+    #[test]
+    fn crlf_and_cr_line_endings() {
+        assert_eq!(
+            decode_chunks(&[b"data: a\r\n\r\ndata: b\r\rdata: c\n\n"]),
+            vec![message("a"), message("b"), message("c")]
+        );
+    }
+
+    #[test]
+    fn crlf_split_across_chunks_is_one_line_ending() {
+        // A stray extra line ending would dispatch "a" on its own
+        assert_eq!(decode_chunks(&[b"data: a\r", b"\ndata: b\r", b"\n\r\n"]), vec![message("a\nb")]);
+    }
+
+    #[test]
+    fn every_split_point_gives_the_same_events() {
+        let stream = "event: delta\nid: 7\ndata: {\"text\":\"你好, world\"}\n\n: keep-alive\n\ndata: [DONE]\n\n".as_bytes();
+        let expected = decode_chunks(&[stream]);
+        assert_eq!(expected.len(), 2);
+        for split in 0..=stream.len() {
+            let (a, b) = stream.split_at(split);
+            assert_eq!(decode_chunks(&[a, b]), expected, "split at byte {}", split);
+        }
+        let bytewise: Vec<&[u8]> = stream.chunks(1).collect();
+        assert_eq!(decode_chunks(&bytewise), expected);
+    }
+
+    #[test]
+    fn utf8_split_inside_a_character() {
+        let bytes = "data: 翻译\n\n".as_bytes();
+        // "翻" is three bytes starting right after "data: "
+        assert_eq!(decode_chunks(&[&bytes[..7], &bytes[7..]]), vec![message("翻译")]);
+    }
+
+    #[test]
+    fn event_type_and_id() {
+        let events = decode_chunks(&[b"event: error\nid: 42\ndata: {\"error\":\"overloaded\"}\n\ndata: next\n\n"]);
+        assert_eq!(events[0].event, "error");
+        assert_eq!(events[0].id.as_deref(), Some("42"));
+        assert_eq!(events[0].data, "{\"error\":\"overloaded\"}");
+        // The type resets after dispatch, the last ID does not
+        assert_eq!(events[1].event, "message");
+        assert_eq!(events[1].id.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn comments_and_unknown_fields_are_ignored() {
+        assert_eq!(decode_chunks(&[b": ping\nretry: 1000\nfoo: bar\ndata: x\n\n"]), vec![message("x")]);
+    }
+
+    #[test]
+    fn value_without_space_and_field_without_colon() {
+        assert_eq!(decode_chunks(&[b"data:tight\ndata\n\n"]), vec![message("tight\n")]);
+        // Only one leading space is stripped
+        assert_eq!(decode_chunks(&[b"data:  two spaces\n\n"]), vec![message(" two spaces")]);
+    }
+
+    #[test]
+    fn blank_lines_without_data_dispatch_nothing() {
+        assert_eq!(decode_chunks(&[b"\n\nevent: ping\n\ndata: x\n\n"]), vec![message("x")]);
+    }
+
+    #[test]
+    fn unterminated_last_event_is_flushed() {
+        assert_eq!(decode_chunks(&[b"data: a\n\ndata: b"]), vec![message("a"), message("b")]);
+        assert_eq!(decode_chunks(&[b"data: a\n"]), vec![message("a")]);
+    }
+
+    #[test]
+    fn leading_bom_is_skipped() {
+        assert_eq!(decode_chunks(&["\u{feff}data: x\n\n".as_bytes()]), vec![message("x")]);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::config::Config;
+use crate::http::ClientOptions;
 use crate::logger;
+use crate::usage;
+use crate::providers::{self, ApiKey, DictEntry, Language, ModelList, ProviderInfo, PROVIDERS};
 use crate::write_clipboard_string;
 use eframe::egui;
 use once_cell::sync::Lazy;
 use std::sync::{mpsc, Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use std::thread;
 use std::fs;
 use serde::{Deserialize, Serialize};
 use windows::{
@@ -18,6 +20,8 @@ use windows::{
 };
 
 static OUTPUT_SENDER: Lazy<Mutex<Option<mpsc::Sender<UiMessage>>>> = Lazy::new(|| Mutex::new(None));
+/// Messages sent before the event loop starts wait here for it
+static OUTPUT_RECEIVER: Lazy<Mutex<Option<mpsc::Receiver<UiMessage>>>> = Lazy::new(|| Mutex::new(None));
 static LAST_TEXT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
 static HAS_UPDATED: AtomicBool = AtomicBool::new(false);
 static FONTS_SET: AtomicBool = AtomicBool::new(false);
@@ -31,14 +35,24 @@ struct OllamaModel {
     modified_at: Option<String>,
 }
 
+/// One backend's output in the comparison view.
+struct CompareColumn {
+    label: String,
+    text: String,
+    error: Option<String>,
+    done: bool,
+    copied: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct OllamaModelsResponse {
     models: Vec<OllamaModel>,
 }
 
 /// Fetch available models from local Ollama installation
-async fn fetch_ollama_models(api_base: &str) -> Result<Vec<OllamaModel>, String> {
-    let client = reqwest::Client::builder()
+async fn fetch_ollama_models(api_base: &str, options: &ClientOptions) -> Result<Vec<OllamaModel>, String> {
+    let client = crate::http::builder(options)
+        .map_err(|e| e.to_string())?
         .timeout(Duration::from_secs(5))
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
@@ -46,8 +60,9 @@ async fn fetch_ollama_models(api_base: &str) -> Result<Vec<OllamaModel>, String>
     let url = format!("{}/api/tags", api_base);
     logger::log(&format!("Fetching Ollama models from: {}", url));
     
-    let response = client
-        .get(&url)
+    let response = options
+        .apply(client.get(&url))
+        .map_err(|e| e.to_string())?
         .send()
         .await
         .map_err(|e| format!("Failed to connect to Ollama: {}", e))?;
@@ -105,62 +120,34 @@ enum UiMessage {
     OpenSettings,
     AppendText(String),  // For streaming updates
     SetTranslating(bool), // Show/hide loading indicator
+    SetProvider(String), // Backend currently producing the output
+    Cancelled, // Translation stopped by the user; keep the partial output
+    SetPending(usize), // Jobs queued behind the running one
+    SetProgress(Option<(usize, usize)>), // Segments done out of the total for long texts
+    SetStatus(Option<String>), // Transient note next to the title, e.g. a retry countdown
+    SetUsage(usage::Summary), // Tokens and cost of the finished translation
+    SetSourceLang(Option<String>), // Source language code detected by the backend
+    Untranslated, // The text is already in the target language and was left as it is
+    ShowEntries(Vec<DictEntry>), // Dictionary entries for a single word
+    StartCompare(Vec<String>), // Switch to side-by-side columns with these labels
+    AppendColumn(usize, String), // Streamed chunk for one comparison column
+    FinishColumn(usize, Option<String>), // Column finished, with an error if it failed
     OllamaModelsLoaded(Vec<OllamaModel>), // Ollama models fetched successfully
     OllamaModelsError(String), // Error fetching Ollama models
+    LanguagesLoaded(Vec<Language>), // Target languages fetched from the backend
+    LanguagesError(String), // Error fetching target languages
 }
 
-fn ensure_output_thread() {
-    let mut guard = OUTPUT_SENDER.lock().unwrap();
-    if guard.is_some() {
-        return;
-    }
+/// Connect the worker threads to the window, before any of them starts.
+/// Messages sent while nothing is connected (e.g. backends under test) are
+/// dropped.
+pub fn connect() {
     let (tx, rx) = mpsc::channel::<UiMessage>();
-    *guard = Some(tx);
-
-    thread::spawn(move || {
-        logger::log("Output UI thread: starting");
-        let mut app = OutputApp { 
-            text: String::new(), 
-            rx, 
-            need_focus: false, 
-            show_settings: false,
-            settings_api_key: String::new(),
-            settings_model: String::new(),
-            settings_lang: String::new(),
-            settings_hotkey: String::new(),
-            settings_api_type: String::new(),
-            settings_api_base: String::new(),
-            is_translating: false,
-            selected_api_type: 0,
-            selected_model: 0,
-            ollama_models: Vec::new(),
-            ollama_models_loading: false,
-            ollama_models_error: None,
-        };
-        // Initialize with current config
-        app.sync_from_config();
-        
-        let native_options = eframe::NativeOptions {
-            viewport: egui::ViewportBuilder::default()
-                .with_title("Echo - Translation")
-                .with_inner_size([800.0, 560.0])
-                .with_always_on_top()
-                .with_visible(false),
-            ..Default::default()
-        };
-        match eframe::run_native(
-            "Echo - Translation",
-            native_options,
-            Box::new(|_cc| Box::new(app)),
-        ) {
-            Ok(_) => logger::log("Output UI thread: stopped"),
-            Err(e) => logger::log(&format!("Output window error: {}", e)),
-        }
-    });
+    *OUTPUT_SENDER.lock().unwrap() = Some(tx);
+    *OUTPUT_RECEIVER.lock().unwrap() = Some(rx);
 }
 
 pub fn show_output_text(text: String) {
-    ensure_output_thread();
     if let Ok(guard) = OUTPUT_SENDER.lock() {
         if let Some(tx) = guard.as_ref() {
             let _ = tx.send(UiMessage::ShowText(text.clone()));
@@ -171,7 +158,6 @@ pub fn show_output_text(text: String) {
 }
 
 pub fn append_text(text: String) {
-    ensure_output_thread();
     if let Ok(guard) = OUTPUT_SENDER.lock() {
         if let Some(tx) = guard.as_ref() {
             let _ = tx.send(UiMessage::AppendText(text));
@@ -180,7 +166,6 @@ pub fn append_text(text: String) {
 }
 
 pub fn set_translating(translating: bool) {
-    ensure_output_thread();
     if let Ok(guard) = OUTPUT_SENDER.lock() {
         if let Some(tx) = guard.as_ref() {
             let _ = tx.send(UiMessage::SetTranslating(translating));
@@ -191,8 +176,116 @@ pub fn set_translating(translating: bool) {
     }
 }
 
+/// Name the backend the output window is showing results from.
+pub fn set_provider(name: &str) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::SetProvider(name.to_string()));
+        }
+    }
+}
+
+/// Mark the output as cancelled, keeping whatever was streamed so far.
+pub fn mark_cancelled() {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::Cancelled);
+        }
+    }
+}
+
+/// Show a transient status (e.g. "Retrying in 3s") in the title bar, or clear it.
+pub fn set_status(status: Option<String>) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::SetStatus(status));
+        }
+    }
+}
+
+/// Show the tokens and cost of the translation in the window footer.
+pub fn set_usage(summary: usage::Summary) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::SetUsage(summary));
+        }
+    }
+}
+
+/// Note next to the source language that the text was left untranslated
+/// because it is already in the target language.
+pub fn mark_untranslated() {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::Untranslated);
+        }
+    }
+}
+
+/// Show the source language the backend detected next to its name.
+pub fn set_source_lang(lang: Option<String>) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::SetSourceLang(lang));
+        }
+    }
+}
+
+/// Show how many translation jobs are waiting behind the current one.
+pub fn set_pending(count: usize) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::SetPending(count));
+        }
+    }
+}
+
+/// Show how many segments of a long text are done, or hide the indicator.
+pub fn set_progress(progress: Option<(usize, usize)>) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::SetProgress(progress));
+        }
+    }
+}
+
+/// Show dictionary entries in the structured panel instead of plain text.
+pub fn show_entries(entries: Vec<DictEntry>) {
+    let text = entries.iter().map(DictEntry::to_plain_text).collect::<Vec<_>>().join("\n\n");
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::ShowEntries(entries));
+        }
+    }
+    if let Ok(mut lt) = LAST_TEXT.lock() { *lt = text; }
+}
+
+/// Show one column per backend label for a side-by-side comparison.
+pub fn start_compare(labels: Vec<String>) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::StartCompare(labels));
+        }
+    }
+}
+
+pub fn append_column(index: usize, text: String) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::AppendColumn(index, text));
+        }
+    }
+}
+
+pub fn finish_column(index: usize, error: Option<String>) {
+    if let Ok(guard) = OUTPUT_SENDER.lock() {
+        if let Some(tx) = guard.as_ref() {
+            let _ = tx.send(UiMessage::FinishColumn(index, error));
+        }
+    }
+}
+
 pub fn show_settings() {
-    ensure_output_thread();
     if let Ok(guard) = OUTPUT_SENDER.lock() {
         if let Some(tx) = guard.as_ref() {
             let _ = tx.send(UiMessage::OpenSettings);
@@ -202,7 +295,6 @@ pub fn show_settings() {
 }
 
 pub fn show_translation_window() {
-    ensure_output_thread();
     let text = { LAST_TEXT.lock().unwrap().clone() };
     if let Ok(guard) = OUTPUT_SENDER.lock() {
         if let Some(tx) = guard.as_ref() {
@@ -233,7 +325,35 @@ struct OutputApp {
     settings_hotkey: String,
     settings_api_type: String,
     settings_api_base: String,
+    settings_deepl_formality: String,
+    settings_deepl_glossary_id: String,
+    settings_deepl_source_lang: String,
+    settings_azure_deployment: String,
+    settings_azure_api_version: String,
+    settings_local_model_path: String,
+    settings_local_tokenizer_path: String,
+    settings_local_threads: String,
+    settings_proxy_url: String,
+    settings_proxy_username: String,
+    settings_proxy_password: String,
+    settings_no_proxy: String,
+    settings_ca_certs: String, // One path per line
+    settings_client_cert: String,
+    settings_client_key: String,
+    settings_allow_invalid_certs: bool,
     is_translating: bool,
+    active_provider: Option<String>,
+    status: Option<String>,
+    pending: usize,
+    progress: Option<(usize, usize)>,
+    usage: Option<usage::Summary>,
+    source_lang: Option<String>,
+    untranslated: bool,
+    // Side-by-side results, empty outside compare mode
+    columns: Vec<CompareColumn>,
+    // Dictionary lookup result, empty unless a single word was looked up
+    entries: Vec<DictEntry>,
+    compare_mode: bool,
     // Dropdown selections
     selected_api_type: usize,
     selected_model: usize,
@@ -241,6 +361,10 @@ struct OutputApp {
     ollama_models: Vec<OllamaModel>,
     ollama_models_loading: bool,
     ollama_models_error: Option<String>,
+    // Target languages offered by backends that can list them
+    languages: Vec<Language>,
+    languages_loading: bool,
+    languages_error: Option<String>,
 }
 
 impl eframe::App for OutputApp {
@@ -251,8 +375,11 @@ impl eframe::App for OutputApp {
             logger::log("Output window: update entered");
         }
         
-        // Handle ESC key to hide window
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        // Handle ESC key: stop a running translation first, otherwise hide window
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) && self.is_translating {
+            crate::cancel::cancel();
+            logger::log("Output window: translation cancelled by ESC key");
+        } else if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
             // Move window off-screen instead of hiding it to keep event loop running
             ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(-10000.0, -10000.0)));
             ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(1.0, 1.0)));
@@ -299,6 +426,8 @@ impl eframe::App for OutputApp {
             match msg {
                 UiMessage::ShowText(new_text) => {
             self.text = new_text;
+                    self.columns.clear();
+                    self.entries.clear();
             self.need_focus = true;
                     self.show_settings = false;
                     self.is_translating = false;
@@ -310,12 +439,83 @@ impl eframe::App for OutputApp {
                 }
                 UiMessage::SetTranslating(translating) => {
                     self.is_translating = translating;
+                    self.status = None;
+                    self.progress = None;
                     if translating {
+                        self.usage = None;
+                        self.source_lang = None;
+                        self.untranslated = false;
                         self.text = String::from("🔄 Translating...");
+                        self.columns.clear();
+                        self.entries.clear();
                         self.need_focus = true;
                         self.show_settings = false;
                     }
                 }
+                UiMessage::SetProvider(name) => {
+                    self.active_provider = Some(name);
+                }
+                UiMessage::SetPending(count) => {
+                    self.pending = count;
+                }
+                UiMessage::SetProgress(progress) => {
+                    self.progress = progress;
+                }
+                UiMessage::SetStatus(status) => {
+                    self.status = status;
+                }
+                UiMessage::SetUsage(summary) => {
+                    self.usage = Some(summary);
+                }
+                UiMessage::SetSourceLang(lang) => {
+                    self.source_lang = lang;
+                }
+                UiMessage::Untranslated => {
+                    self.untranslated = true;
+                }
+                UiMessage::Cancelled => {
+                    self.is_translating = false;
+                    self.status = None;
+                    const MARK: &str = "⏹ Cancelled";
+                    if self.text.is_empty() || self.text == "🔄 Translating..." {
+                        self.text = MARK.to_string();
+                    } else {
+                        self.text.push_str(&format!("\n\n{}", MARK));
+                    }
+                    for column in self.columns.iter_mut().filter(|c| !c.done) {
+                        column.done = true;
+                        column.text.push_str(&format!("\n\n{}", MARK));
+                    }
+                    if let Ok(mut lt) = LAST_TEXT.lock() { *lt = self.text.clone(); }
+                }
+                UiMessage::ShowEntries(entries) => {
+                    self.text = entries.iter().map(DictEntry::to_plain_text).collect::<Vec<_>>().join("\n\n");
+                    self.entries = entries;
+                    self.columns.clear();
+                    self.active_provider = None;
+                    self.need_focus = true;
+                    self.show_settings = false;
+                    self.is_translating = false;
+                }
+                UiMessage::StartCompare(labels) => {
+                    self.active_provider = None;
+                    self.entries.clear();
+                    self.columns = labels
+                        .into_iter()
+                        .map(|label| CompareColumn { label, text: String::new(), error: None, done: false, copied: false })
+                        .collect();
+                }
+                UiMessage::AppendColumn(index, chunk) => {
+                    if let Some(column) = self.columns.get_mut(index) {
+                        column.text.push_str(&chunk);
+                    }
+                }
+                UiMessage::FinishColumn(index, error) => {
+                    if let Some(column) = self.columns.get_mut(index) {
+                        column.done = true;
+                        column.error = error;
+                    }
+                }
                 UiMessage::OpenSettings => {
                     self.show_settings = true;
                     self.need_focus = true;
@@ -345,6 +545,17 @@ impl eframe::App for OutputApp {
                     self.ollama_models_error = Some(error);
                     logger::log("UI: Failed to load Ollama models");
                 }
+                UiMessage::LanguagesLoaded(languages) => {
+                    self.languages = languages;
+                    self.languages_loading = false;
+                    self.languages_error = None;
+                    logger::log(&format!("UI: Loaded {} target languages", self.languages.len()));
+                }
+                UiMessage::LanguagesError(error) => {
+                    self.languages_loading = false;
+                    self.languages_error = Some(error);
+                    logger::log("UI: Failed to load target languages");
+                }
             }
         }
 
@@ -396,6 +607,56 @@ impl eframe::App for OutputApp {
 }
 
 impl OutputApp {
+    fn new(rx: mpsc::Receiver<UiMessage>) -> Self {
+        Self {
+            text: String::new(),
+            rx,
+            need_focus: false,
+            show_settings: false,
+            settings_api_key: String::new(),
+            settings_model: String::new(),
+            settings_lang: String::new(),
+            settings_hotkey: String::new(),
+            settings_api_type: String::new(),
+            settings_api_base: String::new(),
+            settings_deepl_formality: String::new(),
+            settings_deepl_glossary_id: String::new(),
+            settings_deepl_source_lang: String::new(),
+            settings_azure_deployment: String::new(),
+            settings_azure_api_version: String::new(),
+            settings_local_model_path: String::new(),
+            settings_local_tokenizer_path: String::new(),
+            settings_local_threads: String::new(),
+            settings_proxy_url: String::new(),
+            settings_proxy_username: String::new(),
+            settings_proxy_password: String::new(),
+            settings_no_proxy: String::new(),
+            settings_ca_certs: String::new(),
+            settings_client_cert: String::new(),
+            settings_client_key: String::new(),
+            settings_allow_invalid_certs: false,
+            is_translating: false,
+            active_provider: None,
+            status: None,
+            pending: 0,
+            progress: None,
+            usage: None,
+            source_lang: None,
+            untranslated: false,
+            columns: Vec::new(),
+            entries: Vec::new(),
+            compare_mode: false,
+            selected_api_type: 0,
+            selected_model: 0,
+            ollama_models: Vec::new(),
+            ollama_models_loading: false,
+            ollama_models_error: None,
+            languages: Vec::new(),
+            languages_loading: false,
+            languages_error: None,
+        }
+    }
+
     fn sync_from_config(&mut self) {
         if let Ok(cfg_guard) = CONFIG.lock() {
             if let Some(cfg_arc) = cfg_guard.as_ref() {
@@ -404,31 +665,103 @@ impl OutputApp {
                     self.settings_model = cfg.openai_model.clone();
                     self.settings_lang = cfg.target_lang.clone();
                     self.settings_hotkey = cfg.hotkey.clone();
+                    self.compare_mode = cfg.compare_mode;
                     self.settings_api_type = cfg.api_type.clone();
                     self.settings_api_base = cfg.api_base.clone();
+                    self.settings_deepl_formality = cfg.deepl_formality.clone();
+                    self.settings_deepl_glossary_id = cfg.deepl_glossary_id.clone();
+                    self.settings_deepl_source_lang = cfg.deepl_source_lang.clone();
+                    self.settings_azure_deployment = cfg.azure_deployment.clone();
+                    self.settings_azure_api_version = cfg.azure_api_version.clone();
+                    self.settings_local_model_path = cfg.local_model_path.clone();
+                    self.settings_local_tokenizer_path = cfg.local_tokenizer_path.clone();
+                    self.settings_local_threads = cfg.local_threads.map(|n| n.to_string()).unwrap_or_default();
+                    self.settings_proxy_url = cfg.proxy_url.clone();
+                    self.settings_proxy_username = cfg.proxy_username.clone();
+                    self.settings_proxy_password = cfg.proxy_password.clone();
+                    self.settings_no_proxy = cfg.no_proxy.clone();
+                    self.settings_ca_certs = cfg.ca_certs.join("\n");
+                    self.settings_client_cert = cfg.client_cert.clone();
+                    self.settings_client_key = cfg.client_key.clone();
+                    self.settings_allow_invalid_certs = cfg.allow_invalid_certs;
                     
-                    self.selected_api_type = match cfg.api_type.as_str() {
-                        "openai" => 0,
-                        "ollama" => 1,
-                        "google_free" => 2,
-                        _ => 0,
-                    };
-                    
-                    // Load Ollama models if API type is Ollama
-                    if self.selected_api_type == 1 {
-                        self.load_ollama_models();
-                    }
+                    self.selected_api_type = providers::index_of(&cfg.api_type);
                     
                     // Set model selection based on config
-                    if self.selected_api_type == 0 {
-                        self.selected_model = 0; // OpenAI always has one model
-                    } else if self.selected_api_type == 1 {
-                        // For Ollama, try to match the current model string to an index
-                        if !self.ollama_models.is_empty() {
+                    match self.selected_provider().models {
+                        ModelList::Fixed(models) => {
+                            self.selected_model = models.iter().position(|(id, _)| *id == cfg.openai_model).unwrap_or(0);
+                        }
+                        ModelList::Ollama => {
+                            // Load Ollama models, then try to match the current model string to an index
+                            self.load_ollama_models();
                             if let Some(index) = self.ollama_models.iter().position(|m| m.name == cfg.openai_model) {
                                 self.selected_model = index;
                             }
                         }
+                        ModelList::None => {}
+                    }
+
+                    if self.selected_provider().capabilities.list_languages {
+                        self.load_languages();
+                    }
+                }
+            }
+        }
+    }
+
+    fn selected_provider(&self) -> &'static ProviderInfo {
+        PROVIDERS.get(self.selected_api_type).copied().unwrap_or(PROVIDERS[0])
+    }
+
+    /// Client options from the network settings being edited, before they are saved.
+    fn settings_client_options(&self) -> ClientOptions {
+        ClientOptions::from_config(&self.settings_network_config())
+    }
+
+    /// The network settings being edited, on top of the defaults, for
+    /// building a provider that reaches the backend the way the saved
+    /// settings will.
+    fn settings_network_config(&self) -> Config {
+        // Extra headers and query params are only set in config.json
+        let (extra_headers, extra_query) = CONFIG
+            .lock()
+            .ok()
+            .and_then(|guard| guard.as_ref().and_then(|cfg| cfg.lock().ok().map(|cfg| (cfg.extra_headers.clone(), cfg.extra_query.clone()))))
+            .unwrap_or_default();
+        Config {
+            proxy_url: self.settings_proxy_url.clone(),
+            proxy_username: self.settings_proxy_username.clone(),
+            proxy_password: self.settings_proxy_password.clone(),
+            no_proxy: self.settings_no_proxy.clone(),
+            ca_certs: self.settings_ca_cert_paths(),
+            client_cert: self.settings_client_cert.clone(),
+            client_key: self.settings_client_key.clone(),
+            allow_invalid_certs: self.settings_allow_invalid_certs,
+            extra_headers,
+            extra_query,
+            ..Config::default()
+        }
+    }
+
+    fn settings_ca_cert_paths(&self) -> Vec<String> {
+        self.settings_ca_certs
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+
+    /// Turn the side-by-side comparison on or off for the next translation.
+    fn set_compare_mode(&mut self, on: bool) {
+        self.compare_mode = on;
+        if let Ok(cfg_guard) = CONFIG.lock() {
+            if let Some(cfg_arc) = cfg_guard.as_ref() {
+                if let Ok(mut cfg) = cfg_arc.lock() {
+                    cfg.compare_mode = on;
+                    match cfg.save() {
+                        Ok(_) => logger::log(&format!("Compare mode {}", if on { "enabled" } else { "disabled" })),
+                        Err(e) => logger::log(&format!("Failed to save settings: {}", e)),
                     }
                 }
             }
@@ -436,45 +769,45 @@ impl OutputApp {
     }
 
     fn save_to_config(&mut self) {
+        let info = self.selected_provider();
         if let Ok(cfg_guard) = CONFIG.lock() {
             if let Some(cfg_arc) = cfg_guard.as_ref() {
                 if let Ok(mut cfg) = cfg_arc.lock() {
-                    let new_api_type = match self.selected_api_type {
-                        0 => "openai",
-                        1 => "ollama",
-                        2 => "google_free",
-                        _ => "openai",
-                    };
-                    
-                    cfg.api_type = new_api_type.to_string();
+                    cfg.api_type = info.id.to_string();
                     self.settings_api_type = cfg.api_type.clone();
                     
-                    // Auto-configure base URL and Model
-                    if self.selected_api_type == 1 { // Ollama
-                        if cfg.api_base.is_empty() || cfg.api_base == "https://api.openai.com/v1" {
-                             cfg.api_base = "http://localhost:11434".to_string();
-                             self.settings_api_base = cfg.api_base.clone();
-                        }
-                        // If current model is OpenAI default, switch to a default Ollama model
-                        // We can't rely on loaded models yet as they might be loading async
-                        if cfg.openai_model == "gpt-4o-mini" {
-                            cfg.openai_model = "gemma3:1b".to_string();
-                            self.settings_model = cfg.openai_model.clone();
-                        } else {
-                            // If we have a selected model index and it's valid, use it
-                            if !self.ollama_models.is_empty() && self.selected_model < self.ollama_models.len() {
+                    // Auto-configure base URL unless the user set a custom one
+                    if !info.default_api_base.is_empty() && (cfg.api_base.is_empty() || providers::is_default_api_base(&cfg.api_base)) {
+                        cfg.api_base = info.default_api_base.to_string();
+                        self.settings_api_base = cfg.api_base.clone();
+                    }
+
+                    // Auto-configure model
+                    match info.models {
+                        ModelList::Ollama => {
+                            // If current model is another backend's default, switch to a default Ollama model
+                            // We can't rely on loaded models yet as they might be loading async
+                            if cfg.openai_model != info.default_model && providers::is_default_model(&cfg.openai_model) {
+                                cfg.openai_model = info.default_model.to_string();
+                                self.settings_model = cfg.openai_model.clone();
+                            } else if self.selected_model < self.ollama_models.len() {
+                                // If we have a selected model index and it's valid, use it
                                 cfg.openai_model = self.ollama_models[self.selected_model].name.clone();
                                 self.settings_model = cfg.openai_model.clone();
                             }
                         }
-                    } else if self.selected_api_type == 0 { // OpenAI
-                        if cfg.api_base.is_empty() || cfg.api_base == "http://localhost:11434" {
-                             cfg.api_base = "https://api.openai.com/v1".to_string();
-                             self.settings_api_base = cfg.api_base.clone();
+                        ModelList::Fixed(models) => {
+                            // Keep the chosen model if this backend offers it
+                            match models.iter().position(|(id, _)| *id == cfg.openai_model) {
+                                Some(index) => self.selected_model = index,
+                                None => {
+                                    cfg.openai_model = info.default_model.to_string();
+                                    self.selected_model = models.iter().position(|(id, _)| *id == info.default_model).unwrap_or(0);
+                                }
+                            }
+                            self.settings_model = cfg.openai_model.clone();
                         }
-                        // Always ensure OpenAI model is valid
-                        cfg.openai_model = "gpt-4o-mini".to_string();
-                        self.settings_model = cfg.openai_model.clone();
+                        ModelList::None => {}
                     }
 
                     match cfg.save() {
@@ -495,12 +828,13 @@ impl OutputApp {
         self.ollama_models_error = None;
         
         let api_base = self.settings_api_base.clone();
+        let options = self.settings_client_options();
         let rt = tokio::runtime::Runtime::new().unwrap();
         
         // Spawn async task to fetch models
         std::thread::spawn(move || {
             let models = rt.block_on(async {
-                fetch_ollama_models(&api_base).await
+                fetch_ollama_models(&api_base, &options).await
             });
             
             // Send result back to UI thread
@@ -519,6 +853,41 @@ impl OutputApp {
         });
     }
 
+    /// Fetch target languages from the backend currently selected in settings.
+    fn load_languages(&mut self) {
+        if self.languages_loading {
+            return; // Already loading
+        }
+
+        self.languages_loading = true;
+        self.languages_error = None;
+        self.languages.clear();
+
+        // Build the provider from the settings being edited, not the saved config,
+        // with the same proxy and certificates as the Ollama model list
+        let cfg = Config {
+            api_type: self.selected_provider().id.to_string(),
+            api_base: self.settings_api_base.clone(),
+            openai_api_key: self.settings_api_key.clone(),
+            ..self.settings_network_config()
+        };
+        let provider = providers::from_config(&cfg);
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        std::thread::spawn(move || {
+            let languages = rt.block_on(provider.languages());
+
+            if let Ok(guard) = OUTPUT_SENDER.lock() {
+                if let Some(tx) = guard.as_ref() {
+                    let _ = match languages {
+                        Ok(languages) => tx.send(UiMessage::LanguagesLoaded(languages)),
+                        Err(e) => tx.send(UiMessage::LanguagesError(e.to_string())),
+                    };
+                }
+            }
+        });
+    }
+
     fn show_translation_ui(&mut self, ctx: &egui::Context) {
         // Set custom style for better text rendering
         let mut style = (*ctx.style()).clone();
@@ -587,6 +956,41 @@ impl OutputApp {
                             ui.label(egui::RichText::new("📝 Echo")
                                 .size(18.0)
                                 .color(egui::Color32::from_rgb(138, 180, 248)));
+                            if let Some(provider) = &self.active_provider {
+                                ui.label(egui::RichText::new(format!("via {}", provider))
+                                    .size(12.0)
+                                    .color(egui::Color32::from_rgb(150, 160, 180)));
+                            }
+                            if let Some(lang) = &self.source_lang {
+                                if self.untranslated {
+                                    ui.label(egui::RichText::new(format!("already in {}, not translated", lang))
+                                        .size(12.0)
+                                        .color(egui::Color32::from_rgb(250, 204, 21)))
+                                        .on_hover_text("The text is already in the target language and is shown as it is");
+                                } else {
+                                    ui.label(egui::RichText::new(format!("from {}", lang))
+                                        .size(12.0)
+                                        .color(egui::Color32::from_rgb(150, 160, 180)))
+                                        .on_hover_text("Source language detected by the backend");
+                                }
+                            }
+                            if let Some(status) = &self.status {
+                                ui.label(egui::RichText::new(status)
+                                    .size(12.0)
+                                    .color(egui::Color32::from_rgb(250, 204, 21)));
+                            }
+                            if let Some((done, total)) = self.progress {
+                                ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                                    .desired_width(80.0)
+                                    .text(format!("{}/{}", done, total)))
+                                    .on_hover_text("Segments of the text translated so far");
+                            }
+                            if self.pending > 0 {
+                                ui.label(egui::RichText::new(format!("+{} queued", self.pending))
+                                    .size(12.0)
+                                    .color(egui::Color32::from_rgb(150, 160, 180)))
+                                    .on_hover_text("Translations waiting for the current one to finish");
+                            }
                             
                             // Right-aligned controls
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -660,6 +1064,52 @@ impl OutputApp {
                                     logger::log("Text copied to clipboard");
                                 }
 
+                                // Stop button while a translation is running
+                                if self.is_translating {
+                                    let stop_btn_size = egui::vec2(36.0, 36.0);
+                                    let (stop_rect, stop_resp) = ui.allocate_at_least(stop_btn_size, egui::Sense::click());
+                                    if stop_resp.hovered() {
+                                        ui.painter().rect_filled(
+                                            stop_rect,
+                                            egui::Rounding::same(6.0),
+                                            egui::Color32::from_rgb(239, 68, 68),
+                                        );
+                                    }
+                                    ui.painter().text(
+                                        stop_rect.center(),
+                                        egui::Align2::CENTER_CENTER,
+                                        egui_phosphor::regular::STOP,
+                                        egui::FontId::proportional(16.0),
+                                        if stop_resp.hovered() { egui::Color32::WHITE } else { egui::Color32::from_rgb(255, 100, 100) },
+                                    );
+                                    if stop_resp.on_hover_text("Stop translating (Esc)").clicked() {
+                                        crate::cancel::cancel();
+                                        logger::log("Output window: translation cancelled by Stop button");
+                                    }
+                                }
+
+                                // Compare mode toggle
+                                let compare_btn_size = egui::vec2(36.0, 36.0);
+                                let (compare_rect, compare_resp) = ui.allocate_at_least(compare_btn_size, egui::Sense::click());
+                                if compare_resp.hovered() || self.compare_mode {
+                                    ui.painter().rect_filled(
+                                        compare_rect,
+                                        egui::Rounding::same(6.0),
+                                        if self.compare_mode { egui::Color32::from_rgb(67, 97, 238) } else { egui::Color32::from_rgb(55, 60, 70) },
+                                    );
+                                }
+                                ui.painter().text(
+                                    compare_rect.center(),
+                                    egui::Align2::CENTER_CENTER,
+                                    egui_phosphor::regular::COLUMNS,
+                                    egui::FontId::proportional(16.0),
+                                    if compare_resp.hovered() || self.compare_mode { egui::Color32::WHITE } else { egui::Color32::from_rgb(200, 200, 210) },
+                                );
+                                let compare_resp = compare_resp.on_hover_text("Compare providers side by side (compare_providers in config.json)");
+                                if compare_resp.clicked() {
+                                    self.set_compare_mode(!self.compare_mode);
+                                }
+
                                 ui.add_space(8.0);
                                 ui.separator();
                                 ui.add_space(8.0);
@@ -672,20 +1122,15 @@ impl OutputApp {
                                     |ui| {
                                         egui::ComboBox::from_id_source("title_api_switcher")
                                             .width(combo_width)
-                                            .selected_text(match self.selected_api_type {
-                                                0 => "OpenAI",
-                                                1 => "Ollama",
-                                                2 => "Google",
-                                                _ => "OpenAI"
-                                            })
+                                            .selected_text(self.selected_provider().short_label)
                                             .show_ui(ui, |ui| {
                                                 let mut changed = false;
-                                                if ui.selectable_value(&mut self.selected_api_type, 0, "OpenAI").clicked() { changed = true; }
-                                                if ui.selectable_value(&mut self.selected_api_type, 1, "Ollama").clicked() { changed = true; }
-                                                if ui.selectable_value(&mut self.selected_api_type, 2, "Google").clicked() { changed = true; }
+                                                for (i, info) in PROVIDERS.iter().enumerate() {
+                                                    if ui.selectable_value(&mut self.selected_api_type, i, info.short_label).clicked() { changed = true; }
+                                                }
                                                 
                                                 if changed {
-                                                    if self.selected_api_type == 1 {
+                                                    if matches!(self.selected_provider().models, ModelList::Ollama) {
                                                         self.load_ollama_models();
                                                     }
                                                     self.save_to_config();
@@ -705,6 +1150,19 @@ impl OutputApp {
                         .inner_margin(egui::Margin::symmetric(20.0, 16.0))
                         .rounding(egui::Rounding { nw: 0.0, ne: 0.0, sw: 12.0, se: 12.0 })
                         .show(ui, |ui| {
+                            if let Some(summary) = &self.usage {
+                                egui::TopBottomPanel::bottom("usage_footer")
+                                    .frame(egui::Frame::none().inner_margin(egui::Margin { top: 6.0, ..Default::default() }))
+                                    .show_inside(ui, |ui| Self::show_usage_footer(ui, summary));
+                            }
+                            if !self.columns.is_empty() {
+                                self.show_compare_columns(ui);
+                                return;
+                            }
+                            if !self.entries.is_empty() {
+                                self.show_dictionary_entries(ui);
+                                return;
+                            }
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false])
                 .show(ui, |ui| {
@@ -750,6 +1208,128 @@ impl OutputApp {
             });
     }
 
+    /// Tokens and cost of the last translation, with today's and this
+    /// month's totals.
+    fn show_usage_footer(ui: &mut egui::Ui, summary: &usage::Summary) {
+        let color = egui::Color32::from_rgb(150, 160, 180);
+        let t = &summary.translation;
+        let mut text = format!("{} in + {} out tokens", t.input_tokens, t.output_tokens);
+        if !summary.unpriced {
+            text.push_str(&format!(" · ${:.4}", t.cost));
+        }
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(text).size(12.0).color(color))
+                .on_hover_text(if summary.unpriced {
+                    "No price for this model in config.json (prices)"
+                } else {
+                    "Tokens reported by the backend for this translation"
+                });
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(egui::RichText::new(format!("Today ${:.2} · Month ${:.2}", summary.today.cost, summary.month.cost))
+                    .size(12.0)
+                    .color(color))
+                    .on_hover_text(format!(
+                        "Today: {} tokens\nThis month: {} tokens\nDays are UTC; totals are in usage.json",
+                        summary.today.input_tokens + summary.today.output_tokens,
+                        summary.month.input_tokens + summary.month.output_tokens,
+                    ));
+            });
+        });
+    }
+
+    /// Headword, pronunciation and numbered meanings for each dictionary.
+    fn show_dictionary_entries(&self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (i, entry) in self.entries.iter().enumerate() {
+                    if i > 0 {
+                        ui.add_space(12.0);
+                        ui.separator();
+                        ui.add_space(8.0);
+                    }
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(egui::RichText::new(&entry.headword)
+                            .size(22.0)
+                            .strong()
+                            .color(egui::Color32::from_rgb(220, 225, 235)));
+                        if let Some(pronunciation) = &entry.pronunciation {
+                            ui.label(egui::RichText::new(format!("/{}/", pronunciation))
+                                .size(15.0)
+                                .color(egui::Color32::from_rgb(180, 190, 210)));
+                        }
+                    });
+                    ui.label(egui::RichText::new(&entry.source)
+                        .size(12.0)
+                        .color(egui::Color32::from_rgb(150, 160, 180)));
+                    ui.add_space(6.0);
+
+                    for sense in &entry.senses {
+                        if !sense.part_of_speech.is_empty() {
+                            ui.label(egui::RichText::new(&sense.part_of_speech)
+                                .size(14.0)
+                                .italics()
+                                .color(egui::Color32::from_rgb(138, 180, 248)));
+                        }
+                        for (n, meaning) in sense.meanings.iter().enumerate() {
+                            ui.label(egui::RichText::new(format!("{}. {}", n + 1, meaning))
+                                .size(16.0)
+                                .color(egui::Color32::from_rgb(220, 225, 235)));
+                        }
+                        ui.add_space(4.0);
+                    }
+                }
+            });
+    }
+
+    /// One column per backend; clicking a column's text copies that result.
+    fn show_compare_columns(&mut self, ui: &mut egui::Ui) {
+        let mut copied = None;
+        ui.columns(self.columns.len(), |uis| {
+            for (i, (ui, column)) in uis.iter_mut().zip(self.columns.iter()).enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(&column.label)
+                        .size(13.0)
+                        .color(egui::Color32::from_rgb(138, 180, 248)));
+                    if !column.done {
+                        ui.spinner();
+                    } else if column.copied {
+                        ui.label(egui::RichText::new("Copied")
+                            .size(12.0)
+                            .color(egui::Color32::from_rgb(150, 160, 180)));
+                    }
+                });
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical()
+                    .id_source(("compare_column", i))
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        let text = match &column.error {
+                            Some(error) => egui::RichText::new(format!("❌ Error: {}", error))
+                                .color(egui::Color32::from_rgb(255, 100, 100)),
+                            None => egui::RichText::new(&column.text)
+                                .color(egui::Color32::from_rgb(220, 225, 235)),
+                        };
+                        let resp = ui
+                            .add(egui::Label::new(text.size(16.0)).sense(egui::Sense::click()))
+                            .on_hover_text("Click to copy");
+                        if resp.clicked() && column.error.is_none() && !column.text.is_empty() {
+                            copied = Some(i);
+                        }
+                    });
+            }
+        });
+
+        if let Some(i) = copied {
+            if write_clipboard_string(&self.columns[i].text) {
+                for (j, column) in self.columns.iter_mut().enumerate() {
+                    column.copied = i == j;
+                }
+                logger::log(&format!("Copied comparison result from {}", self.columns[i].label));
+            }
+        }
+    }
+
     fn show_settings_ui(&mut self, ctx: &egui::Context) {
         // Modern settings panel with same styling
         egui::CentralPanel::default()
@@ -820,78 +1400,72 @@ impl OutputApp {
                                 .color(egui::Color32::from_rgb(180, 190, 210)));
                             ui.add_space(4.0);
                             egui::ComboBox::from_id_source("api_type")
-                                .selected_text(match self.selected_api_type {
-                                    0 => "OpenAI",
-                                    1 => "Ollama (Free)",
-                                    2 => "Google (Free)",
-                                    _ => "OpenAI"
-                                })
+                                .selected_text(self.selected_provider().label)
                                 .show_ui(ui, |ui| {
-                                    ui.selectable_value(&mut self.selected_api_type, 0, "OpenAI");
-                                    ui.selectable_value(&mut self.selected_api_type, 1, "Ollama (Free)");
-                                    ui.selectable_value(&mut self.selected_api_type, 2, "Google (Free)");
+                                    for (i, info) in PROVIDERS.iter().enumerate() {
+                                        ui.selectable_value(&mut self.selected_api_type, i, info.label);
+                                    }
                                 });
                             
                             // Update API type and base URL when selection changes
-                            let new_api_type = match self.selected_api_type {
-                                0 => "openai",
-                                1 => "ollama",
-                                2 => "google_free",
-                                _ => "openai",
-                            };
+                            let info = self.selected_provider();
 
-                            if self.settings_api_type != new_api_type {
-                                self.settings_api_type = new_api_type.to_string();
-                                self.settings_api_base = match self.selected_api_type {
-                                    0 => "https://api.openai.com/v1".to_string(),
-                                    1 => "http://localhost:11434".to_string(),
-                                    _ => String::new(), // Not used for google_free
-                                };
+                            if self.settings_api_type != info.id {
+                                self.settings_api_type = info.id.to_string();
+                                self.settings_api_base = info.default_api_base.to_string();
                                 // Reset model selection when API type changes
                                 self.selected_model = 0;
                                 
                                 // Load Ollama models if switching to Ollama
-                                if self.selected_api_type == 1 {
+                                if matches!(info.models, ModelList::Ollama) {
                                     self.load_ollama_models();
                                 }
+                                if info.capabilities.list_languages {
+                                    self.load_languages();
+                                }
                             }
                             
                             ui.add_space(16.0);
 
-                            if self.selected_api_type == 2 {
-                                // Google Free - No extra settings needed
-                                ui.label(egui::RichText::new("ℹ No API key required for Google Translate")
+                            if info.api_key == ApiKey::None && matches!(info.models, ModelList::None) {
+                                // No extra settings needed
+                                ui.label(egui::RichText::new(format!("ℹ No API key required for {}", info.short_label))
                                     .size(13.0)
                                     .color(egui::Color32::from_rgb(138, 180, 248)));
                             } else {
-                                // API Key (Only for OpenAI)
-                                if self.selected_api_type == 0 {
-                                    ui.label(egui::RichText::new("OpenAI API Key")
+                                if info.api_key != ApiKey::None {
+                                    let optional = if info.api_key == ApiKey::Optional { " (optional)" } else { "" };
+                                    ui.label(egui::RichText::new(format!("{} API Key{}", info.short_label, optional))
                                         .size(14.0)
                                         .color(egui::Color32::from_rgb(180, 190, 210)));
                                     ui.add_space(4.0);
                                     ui.add(egui::TextEdit::singleline(&mut self.settings_api_key)
                                         .password(true)
                                         .desired_width(f32::INFINITY)
-                                        .hint_text("sk-..."));
+                                        .hint_text(info.api_key_hint));
                                     
                                     ui.add_space(16.0);
                                 }
                                 
                                 // Model dropdown
-                                ui.label(egui::RichText::new("Model")
-                                    .size(14.0)
-                                    .color(egui::Color32::from_rgb(180, 190, 210)));
-                                ui.add_space(4.0);
+                                if !matches!(info.models, ModelList::None) {
+                                    ui.label(egui::RichText::new("Model")
+                                        .size(14.0)
+                                        .color(egui::Color32::from_rgb(180, 190, 210)));
+                                    ui.add_space(4.0);
+                                }
                                 
-                                if self.selected_api_type == 0 {
-                                    // OpenAI models (static)
+                                if let ModelList::Fixed(models) = info.models {
+                                    // Static model list
+                                    let selected_text = models.get(self.selected_model).map_or("Select a model", |(_, label)| *label);
                                     egui::ComboBox::from_id_source("model")
-                                        .selected_text("GPT-4o Mini")
+                                        .selected_text(selected_text)
                                         .show_ui(ui, |ui| {
-                                            ui.selectable_value(&mut self.selected_model, 0, "GPT-4o Mini");
+                                            for (i, (_, label)) in models.iter().enumerate() {
+                                                ui.selectable_value(&mut self.selected_model, i, *label);
+                                            }
                                     });
-                            } else {
+                            } else if matches!(info.models, ModelList::Ollama) {
                                 // Ollama models (dynamic)
                                 if self.ollama_models_loading {
                                     ui.label(egui::RichText::new("Loading models...")
@@ -926,16 +1500,117 @@ impl OutputApp {
                             }
                             
                             }
+
+                            if info.id == "deepl" {
+                                ui.label(egui::RichText::new("Formality")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                let formality_options = [
+                                    ("", "Default"),
+                                    ("more", "More formal"),
+                                    ("less", "Less formal"),
+                                    ("prefer_more", "More formal if supported"),
+                                    ("prefer_less", "Less formal if supported"),
+                                ];
+                                let selected_text = formality_options
+                                    .iter()
+                                    .find(|(v, _)| *v == self.settings_deepl_formality)
+                                    .map_or(self.settings_deepl_formality.as_str(), |(_, label)| *label)
+                                    .to_string();
+                                egui::ComboBox::from_id_source("deepl_formality")
+                                    .selected_text(selected_text)
+                                    .show_ui(ui, |ui| {
+                                        for (value, label) in formality_options {
+                                            ui.selectable_value(&mut self.settings_deepl_formality, value.to_string(), label);
+                                        }
+                                    });
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("Glossary ID (optional)")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_deepl_glossary_id)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("def3a26b-3e84-45b3-84ae-0c0aaf3525f7"));
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("Source Language (required with a glossary)")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_deepl_source_lang)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("Auto-detect"));
+                            }
+
+                            if info.id == "azure_openai" {
+                                ui.label(egui::RichText::new("Deployment Name")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_azure_deployment)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("gpt-4o-mini"));
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("API Version")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_azure_api_version)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("2024-06-01"));
+                            }
+
+                            if info.id == "local" {
+                                ui.label(egui::RichText::new("GGUF Model File")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_local_model_path)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text(r"C:\models\qwen2.5-1.5b-instruct-q4_k_m.gguf"));
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("Tokenizer File")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_local_tokenizer_path)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("tokenizer.json next to the model"));
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("CPU Threads")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_local_threads)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("All cores"));
+                            }
                             
                             // Update model when selection changes
-                            let new_model = if self.selected_api_type == 0 {
-                                "gpt-4o-mini".to_string()
-                            } else {
-                                if self.selected_model < self.ollama_models.len() {
-                                    self.ollama_models[self.selected_model].name.clone()
-                                } else {
-                                    "gemma3:1b".to_string() // fallback
+                            let new_model = match info.models {
+                                ModelList::Fixed(models) => models
+                                    .get(self.selected_model)
+                                    .map_or(info.default_model, |(id, _)| *id)
+                                    .to_string(),
+                                ModelList::Ollama => {
+                                    if self.selected_model < self.ollama_models.len() {
+                                        self.ollama_models[self.selected_model].name.clone()
+                                    } else {
+                                        info.default_model.to_string() // fallback
+                                    }
                                 }
+                                ModelList::None => self.settings_model.clone(),
                             };
                             if self.settings_model != new_model {
                                 self.settings_model = new_model;
@@ -943,15 +1618,18 @@ impl OutputApp {
                             
                             ui.add_space(16.0);
                             
-                            // API Base URL (read-only, auto-configured)
-                            ui.label(egui::RichText::new("API Base URL (Auto-configured)")
+                            // API Base URL (auto-configured, editable for self-hosted servers)
+                            ui.label(egui::RichText::new("API Base URL")
                                 .size(14.0)
                                 .color(egui::Color32::from_rgb(180, 190, 210)));
                             ui.add_space(4.0);
                             ui.add(egui::TextEdit::singleline(&mut self.settings_api_base)
                                 .desired_width(f32::INFINITY)
-                                .interactive(false)
-                                .hint_text("Automatically set based on API Type"));
+                                .hint_text(if info.id == "azure_openai" {
+                                    "https://my-resource.openai.azure.com"
+                                } else {
+                                    "Automatically set based on API Type"
+                                }));
                             
                             ui.add_space(16.0);
                             
@@ -960,9 +1638,38 @@ impl OutputApp {
                                 .size(14.0)
                                 .color(egui::Color32::from_rgb(180, 190, 210)));
                             ui.add_space(4.0);
-                            ui.add(egui::TextEdit::singleline(&mut self.settings_lang)
-                                .desired_width(f32::INFINITY)
-                                .hint_text("English"));
+                            if info.capabilities.list_languages && !self.languages.is_empty() {
+                                // Languages reported by the backend
+                                let selected_text = self.languages
+                                    .iter()
+                                    .find(|l| l.code == self.settings_lang)
+                                    .map_or(self.settings_lang.clone(), |l| format!("{} ({})", l.name, l.code));
+                                egui::ComboBox::from_id_source("target_lang")
+                                    .selected_text(selected_text)
+                                    .show_ui(ui, |ui| {
+                                        for lang in &self.languages {
+                                            ui.selectable_value(&mut self.settings_lang, lang.code.clone(), format!("{} ({})", lang.name, lang.code));
+                                        }
+                                    });
+                            } else {
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_lang)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("English"));
+                                if info.capabilities.list_languages {
+                                    if self.languages_loading {
+                                        ui.label(egui::RichText::new("Loading languages...")
+                                            .color(egui::Color32::from_rgb(150, 160, 180)));
+                                    } else {
+                                        if let Some(ref error) = self.languages_error {
+                                            ui.label(egui::RichText::new(format!("Error: {}", error))
+                                                .color(egui::Color32::from_rgb(255, 100, 100)));
+                                        }
+                                        if ui.button("🔄 Load languages").clicked() {
+                                            self.load_languages();
+                                        }
+                                    }
+                                }
+                            }
                             
                             ui.add_space(16.0);
                             
@@ -977,6 +1684,95 @@ impl OutputApp {
                             ui.label(egui::RichText::new("Examples: Alt+F3, Ctrl+Shift+T, Win+Q")
                                 .size(11.0)
                                 .color(egui::Color32::from_rgb(120, 130, 150)));
+
+                            ui.add_space(16.0);
+
+                            // Proxy
+                            ui.label(egui::RichText::new("Proxy URL")
+                                .size(14.0)
+                                .color(egui::Color32::from_rgb(180, 190, 210)));
+                            ui.add_space(4.0);
+                            ui.add(egui::TextEdit::singleline(&mut self.settings_proxy_url)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("http://proxy.example.com:8080 or socks5://127.0.0.1:1080"));
+                            ui.label(egui::RichText::new("Empty uses HTTP_PROXY / HTTPS_PROXY / ALL_PROXY from the environment")
+                                .size(11.0)
+                                .color(egui::Color32::from_rgb(120, 130, 150)));
+
+                            if !self.settings_proxy_url.trim().is_empty() {
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("Proxy Username")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_proxy_username)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("Only if the proxy requires it"));
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("Proxy Password")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_proxy_password)
+                                    .desired_width(f32::INFINITY)
+                                    .password(true));
+
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("No Proxy")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_no_proxy)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("localhost,127.0.0.1,::1,.corp.example.com"));
+                            }
+
+                            ui.add_space(16.0);
+
+                            // Certificates
+                            ui.label(egui::RichText::new("CA Certificates")
+                                .size(14.0)
+                                .color(egui::Color32::from_rgb(180, 190, 210)));
+                            ui.add_space(4.0);
+                            ui.add(egui::TextEdit::multiline(&mut self.settings_ca_certs)
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(2)
+                                .hint_text(r"C:\certs\company-root.pem (one PEM file per line)"));
+
+                            ui.add_space(16.0);
+
+                            ui.label(egui::RichText::new("Client Certificate (mTLS)")
+                                .size(14.0)
+                                .color(egui::Color32::from_rgb(180, 190, 210)));
+                            ui.add_space(4.0);
+                            ui.add(egui::TextEdit::singleline(&mut self.settings_client_cert)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("PEM file, only if the server asks for one"));
+
+                            if !self.settings_client_cert.trim().is_empty() {
+                                ui.add_space(16.0);
+
+                                ui.label(egui::RichText::new("Client Key")
+                                    .size(14.0)
+                                    .color(egui::Color32::from_rgb(180, 190, 210)));
+                                ui.add_space(4.0);
+                                ui.add(egui::TextEdit::singleline(&mut self.settings_client_key)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("PEM file; empty if the key is in the certificate file"));
+                            }
+
+                            ui.add_space(16.0);
+
+                            ui.checkbox(&mut self.settings_allow_invalid_certs, "Allow invalid certificates for this backend");
+                            if self.settings_allow_invalid_certs {
+                                ui.label(egui::RichText::new("⚠ INSECURE: certificate checks are off. Anyone on the network can read your API key and texts. Prefer adding the CA above.")
+                                    .size(12.0)
+                                    .color(egui::Color32::from_rgb(255, 100, 100)));
+                            }
                             
                             ui.add_space(24.0);
                             });
@@ -1002,6 +1798,22 @@ impl OutputApp {
                                                 cfg.hotkey = self.settings_hotkey.clone();
                                                 cfg.api_type = self.settings_api_type.clone();
                                                 cfg.api_base = self.settings_api_base.clone();
+                                                cfg.deepl_formality = self.settings_deepl_formality.clone();
+                                                cfg.deepl_glossary_id = self.settings_deepl_glossary_id.clone();
+                                                cfg.deepl_source_lang = self.settings_deepl_source_lang.clone();
+                                                cfg.azure_deployment = self.settings_azure_deployment.clone();
+                                                cfg.azure_api_version = self.settings_azure_api_version.clone();
+                                                cfg.local_model_path = self.settings_local_model_path.trim().to_string();
+                                                cfg.local_tokenizer_path = self.settings_local_tokenizer_path.trim().to_string();
+                                                cfg.local_threads = self.settings_local_threads.trim().parse().ok().filter(|&n| n > 0);
+                                                cfg.proxy_url = self.settings_proxy_url.trim().to_string();
+                                                cfg.proxy_username = self.settings_proxy_username.clone();
+                                                cfg.proxy_password = self.settings_proxy_password.clone();
+                                                cfg.no_proxy = self.settings_no_proxy.trim().to_string();
+                                                cfg.ca_certs = self.settings_ca_cert_paths();
+                                                cfg.client_cert = self.settings_client_cert.trim().to_string();
+                                                cfg.client_key = self.settings_client_key.trim().to_string();
+                                                cfg.allow_invalid_certs = self.settings_allow_invalid_certs;
                                                 
                                                 match cfg.save() {
                                                     Ok(_) => {
@@ -1048,34 +1860,13 @@ impl OutputApp {
 
 // Run the UI event loop on the main thread (blocking)
 pub fn run_ui_main_thread() {
-    let mut guard = OUTPUT_SENDER.lock().unwrap();
-    if guard.is_some() {
-        logger::log("UI already running; run_ui_main_thread called twice");
+    let Some(rx) = OUTPUT_RECEIVER.lock().unwrap().take() else {
+        logger::log("UI not connected or already running; run_ui_main_thread called twice?");
         return;
-    }
-    let (tx, rx) = mpsc::channel::<UiMessage>();
-    *guard = Some(tx);
-    drop(guard);
+    };
 
     logger::log("Main UI: starting event loop");
-    let app = OutputApp { 
-        text: String::new(), 
-        rx, 
-        need_focus: false,
-        show_settings: false,
-        settings_api_key: String::new(),
-        settings_model: String::new(),
-        settings_lang: String::new(),
-        settings_hotkey: String::new(),
-        settings_api_type: String::new(),
-        settings_api_base: String::new(),
-        is_translating: false,
-        selected_api_type: 0,
-        selected_model: 0,
-        ollama_models: Vec::new(),
-        ollama_models_loading: false,
-        ollama_models_error: None,
-    };
+    let app = OutputApp::new(rx);
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_title("GPTTrans")
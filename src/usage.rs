@@ -0,0 +1,220 @@
+//! Tokens spent on translations and what they cost, per translation and per
+//! day. Daily totals are kept in a JSON file, by default usage.json next to
+//! the executable; days are UTC, like the providers' billing.
+
+use crate::config::ModelPrice;
+use crate::{logger, ui};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tokens and their cost in dollars. Only models with a price in
+/// `config.prices` add to the cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// What the translation window footer shows.
+#[derive(Debug, Clone, Copy)]
+pub struct Summary {
+    pub translation: Usage,
+    /// Part of the translation ran on a model without a price
+    pub unpriced: bool,
+    pub today: Usage,
+    pub month: Usage,
+}
+
+struct Current {
+    prices: BTreeMap<String, ModelPrice>,
+    usage: Option<Usage>,
+    unpriced: bool,
+}
+
+/// Daily usage, keyed "YYYY-MM-DD", and the file it is kept in.
+struct Store {
+    path: PathBuf,
+    days: BTreeMap<String, Usage>,
+}
+
+/// The translation being tallied; backends report to nothing without one
+static CURRENT: Mutex<Option<Current>> = Mutex::new(None);
+static STORE: Mutex<Option<Store>> = Mutex::new(None);
+
+/// Adds up the usage of one translation until dropped, then adds it to the
+/// daily totals and shows it in the window with today's and this month's.
+pub struct Tally(());
+
+impl Tally {
+    /// Start tallying, with daily totals kept in `path` (see [`default_path`]).
+    pub fn start(prices: &BTreeMap<String, ModelPrice>, path: &Path) -> Self {
+        let mut store = STORE.lock().unwrap();
+        if store.as_ref().is_none_or(|s| s.path != path) {
+            *store = Some(Store { path: path.to_path_buf(), days: load(path) });
+        }
+        *CURRENT.lock().unwrap() = Some(Current { prices: prices.clone(), usage: None, unpriced: false });
+        Tally(())
+    }
+}
+
+impl Drop for Tally {
+    fn drop(&mut self) {
+        let Some(current) = CURRENT.lock().unwrap().take() else { return };
+        // Backends that do not report tokens leave the footer empty
+        let Some(translation) = current.usage else { return };
+        // Written once per translation rather than per request: a long text
+        // makes dozens of requests
+        let mut store = STORE.lock().unwrap();
+        let (today, month) = store
+            .as_mut()
+            .map(|s| {
+                let day = today();
+                *s.days.entry(day.clone()).or_default() += translation;
+                if let Err(e) = save(&s.path, &s.days) {
+                    logger::log(&format!("Usage: cannot save {}: {}", s.path.display(), e));
+                }
+                totals(&s.days, &day)
+            })
+            .unwrap_or_default();
+        ui::set_usage(Summary { translation, unpriced: current.unpriced, today, month });
+    }
+}
+
+/// Record the tokens a backend reported for one request to `model`. Outside
+/// a [`Tally`] there is nothing to add them to and they are only logged.
+pub fn record(model: &str, input_tokens: u64, output_tokens: u64) {
+    let mut current = CURRENT.lock().unwrap();
+    let Some(current) = current.as_mut() else {
+        logger::log(&format!("Usage: {} input + {} output tokens on {}", input_tokens, output_tokens, model));
+        return;
+    };
+    let price = current.prices.get(model).copied();
+    let usage = Usage {
+        input_tokens,
+        output_tokens,
+        cost: price.map_or(0.0, |p| cost(p, input_tokens, output_tokens)),
+    };
+    *current.usage.get_or_insert_with(Usage::default) += usage;
+    current.unpriced |= price.is_none();
+    logger::log(&format!(
+        "Usage: {} input + {} output tokens on {} (${:.6})",
+        input_tokens, output_tokens, model, usage.cost
+    ));
+}
+
+fn cost(price: ModelPrice, input_tokens: u64, output_tokens: u64) -> f64 {
+    (input_tokens as f64 * price.input + output_tokens as f64 * price.output) / 1_000_000.0
+}
+
+/// Usage on `day` and in its month.
+fn totals(days: &BTreeMap<String, Usage>, day: &str) -> (Usage, Usage) {
+    let today = days.get(day).copied().unwrap_or_default();
+    let mut month = Usage::default();
+    for (_, usage) in days.iter().filter(|(d, _)| d.get(..7) == day.get(..7)) {
+        month += *usage;
+    }
+    (today, month)
+}
+
+fn today() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Calendar date of a day count since 1970-01-01 (Howard Hinnant's algorithm).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// usage.json next to the executable.
+pub fn default_path() -> PathBuf {
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("."));
+    exe.parent().unwrap_or(Path::new(".")).join("usage.json")
+}
+
+fn load(path: &Path) -> BTreeMap<String, Usage> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(path: &Path, days: &BTreeMap<String, Usage>) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(days)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_from_day_counts() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(20_818), (2026, 12, 31));
+    }
+
+    #[test]
+    fn cost_is_per_million_tokens() {
+        let price = ModelPrice { input: 0.15, output: 0.6 };
+        assert!((cost(price, 2_000, 500) - 0.0006).abs() < 1e-12);
+    }
+
+    #[test]
+    fn daily_totals_are_saved_when_the_translation_ends() {
+        let path = std::env::temp_dir().join(format!("echo-usage-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let prices = BTreeMap::from([("gpt-4o-mini".to_string(), ModelPrice { input: 0.15, output: 0.6 })]);
+
+        let tally = Tally::start(&prices, &path);
+        record("gpt-4o-mini", 1_000, 100);
+        record("gpt-4o-mini", 2_000, 200);
+        assert!(!path.exists());
+        drop(tally);
+
+        let days = load(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(days.len(), 1);
+        let saved = days[&today()];
+        assert_eq!((saved.input_tokens, saved.output_tokens), (3_000, 300));
+    }
+
+    #[test]
+    fn month_total_covers_only_that_month() {
+        let usage = |tokens| Usage { input_tokens: tokens, output_tokens: 0, cost: 0.0 };
+        let days = BTreeMap::from([
+            ("2026-09-30".to_string(), usage(1)),
+            ("2026-10-01".to_string(), usage(10)),
+            ("2026-10-16".to_string(), usage(100)),
+        ]);
+        let (today, month) = totals(&days, "2026-10-16");
+        assert_eq!(today.input_tokens, 100);
+        assert_eq!(month.input_tokens, 110);
+        assert_eq!(totals(&days, "2026-11-01"), (Usage::default(), Usage::default()));
+    }
+}